@@ -18,7 +18,7 @@ fn main() -> std::io::Result<()> {
             0 => {
                 // skip all separator lines
                 println!("(-- separator line --)");
-                while record.len() == 0 {
+                while record.is_empty() {
                     if !csv_reader.read_record(&mut record)? {
                         break;
                     }
@@ -51,8 +51,7 @@ fn main() -> std::io::Result<()> {
 mod tests {
     use std::io::Cursor;
 
-    use fio_api::{FioResponse, FioResponseInfo};
-    use fio_api::csvdata::FioTransactionsRecord;
+    use fio_api::{FioResponse, FioResponseInfo, FioTransactionsRecord};
 
     #[test]
     fn test_cursor_twoparts() -> anyhow::Result<()> {
@@ -35,7 +35,7 @@ mod tests {
 
     use chrono::{Datelike, NaiveDate};
 
-    use fio_api::{DomesticSymbolsBuilder, DomesticTransaction, FioClient, FioClientWithImport, FioExportReq, PaymentBuilder, PaymentType, ReportFormat, TxFormat};
+    use fio_api::{Amount, DomesticSymbolsBuilder, DomesticTransaction, FioClient, FioClientWithImport, FioExportReq, PaymentBuilder, PaymentType, ReportFormat, TxFormat};
 
     fn init_logging() {
         std::env::set_var("RUST_LOG", "info,hello_fio_api=debug,fio_api=trace");
@@ -77,16 +77,14 @@ mod tests {
         // curl -S --trace-ascii - -X POST -F "type=xml" -F "token=$(cat .git/fio-test-token)" -F "file=@examples/payment.xml" https://www.fio.cz/ib_api/rest/import/
         let fio = fio_client_rw();
         let payment = fio.new_domestic()
-            .amount(321.45)
+            .amount(Amount::from_hundredths(32145))
             .account_to("2702016516", "2010")
             .vs("20")
             .comment("T1")
             .message_for_recipient("t1")
             ;
         let r = fio.import(payment).await.unwrap();
-        println!("Payment Response:\nStatus={}\n {:?}", r.status(), r);
-        let text = r.text().await.unwrap();
-        println!("Response Text: {}", text);
+        println!("Payment Response: {:?}", r);
     }
 
     #[tokio::test]
@@ -96,23 +94,24 @@ mod tests {
         let fio = fio_client_rw();
         let payments = vec![
             fio.new_domestic()
-                .amount(321.45)
+                .amount(Amount::from_hundredths(32145))
                 .account_to("2702016516", "2010")
                 .vs("123")
                 .comment("T1")
                 .message_for_recipient("t1")
                 .into(),
             fio.new_domestic()
-                .amount(123.45)
+                .amount(Amount::from_hundredths(12345))
                 .account_to("2702016516", "2010")
                 .vs("1010110101")
                 .payment_type(PaymentType::Standard)
                 .into(),
         ];
         let r = fio.import(payments.as_slice()).await.unwrap();
-        println!("Payment Response:\nStatus={}\n {:?}", r.status(), r);
-        let text = r.text().await.unwrap();
-        println!("Response Text: {}", text);
+        println!("Payment Response: {:?}", r);
+        for (index, messages) in r.failed_orders() {
+            println!("Order {} failed: {:?}", index, messages);
+        }
     }
 
     #[tokio::test]
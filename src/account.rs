@@ -0,0 +1,256 @@
+//! Czech bank account numbers, in the `prefix-number/bank` notation.
+//!
+//! Both the prefix and the base number carry a weighted mod-11 checksum, as defined by
+//! ČNB decree 169/2011 Sb.; the bank code is any 4-digit code, see [CzAccount::is_known_bank]
+//! for checking it against the codes assigned by ČNB.
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+use crate::Iban;
+
+/// Weights of the digits of the base number, aligned to the right; the prefix uses the last six of them.
+const WEIGHTS: [u64; 10] = [6, 3, 7, 9, 10, 5, 8, 4, 2, 1];
+
+/// Codes of payment system participants, selected from the list published by ČNB (`Číselník kódů bank`);
+/// the list changes over time, so it only provides names and is not a condition of a valid account.
+const BANKS: &[(&str, &str)] = &[
+    ("0100", "Komerční banka, a.s."),
+    ("0300", "Československá obchodní banka, a. s."),
+    ("0600", "MONETA Money Bank, a.s."),
+    ("0710", "Česká národní banka"),
+    ("0800", "Česká spořitelna, a.s."),
+    ("2010", "Fio banka, a.s."),
+    ("2020", "MUFG Bank (Europe) N.V. Prague Branch"),
+    ("2060", "Citfin, spořitelní družstvo"),
+    ("2070", "TRINITY BANK a.s."),
+    ("2100", "Hypoteční banka, a.s."),
+    ("2200", "Peněžní dům, spořitelní družstvo"),
+    ("2220", "Artesa, spořitelní družstvo"),
+    ("2250", "Banka CREDITAS a.s."),
+    ("2260", "NEY spořitelní družstvo"),
+    ("2275", "Podnikatelská družstevní záložna"),
+    ("2600", "Citibank Europe plc, organizační složka"),
+    ("2700", "UniCredit Bank Czech Republic and Slovakia, a.s."),
+    ("3030", "Air Bank a.s."),
+    ("3050", "BNP Paribas Personal Finance SA, odštěpný závod"),
+    ("3060", "PKO BP S.A., Czech Branch"),
+    ("3500", "ING Bank N.V."),
+    ("4000", "Max banka a.s."),
+    ("4300", "Národní rozvojová banka, a.s."),
+    ("5500", "Raiffeisenbank a.s."),
+    ("5800", "J&T BANKA, a.s."),
+    ("6000", "PPF banka a.s."),
+    ("6100", "Raiffeisenbank a.s."),
+    ("6200", "COMMERZBANK Aktiengesellschaft, pobočka Praha"),
+    ("6210", "mBank S.A., organizační složka"),
+    ("6300", "BNP Paribas S.A., pobočka Česká republika"),
+    ("6363", "Partners Banka, a.s."),
+    ("6700", "Všeobecná úverová banka a.s., pobočka Praha"),
+    ("7910", "Deutsche Bank Aktiengesellschaft Filiale Prag, organizační složka"),
+    ("7950", "Raiffeisen stavební spořitelna a.s."),
+    ("7960", "ČSOB Stavební spořitelna, a.s."),
+    ("7970", "MONETA Stavební Spořitelna, a.s."),
+    ("7990", "Modrá pyramida stavební spořitelna, a.s."),
+    ("8030", "Volksbank Raiffeisenbank Nordoberpfalz eG pobočka Cheb"),
+    ("8040", "Oberbank AG pobočka Česká republika"),
+    ("8060", "Stavební spořitelna České spořitelny, a.s."),
+    ("8090", "Česká exportní banka, a.s."),
+    ("8150", "HSBC Continental Europe, Czech Republic"),
+    ("8190", "Sparkasse Oberlausitz-Niederschlesien"),
+    ("8198", "FAS finance company s.r.o."),
+    ("8199", "MoneyPolo Europe s.r.o."),
+    ("8200", "PRIVAT BANK der Raiffeisenlandesbank Oberösterreich Aktiengesellschaft, pobočka Česká republika"),
+    ("8220", "Payment execution s.r.o."),
+    ("8230", "ABAPAY s.r.o."),
+    ("8250", "Bank of China (CEE) Ltd. Prague Branch"),
+    ("8255", "Bank of Communications Co., Ltd., Prague Branch odštěpný závod"),
+    ("8265", "Industrial and Commercial Bank of China Limited, Prague Branch, odštěpný závod"),
+    ("8270", "Fairplay Pay s.r.o."),
+    ("8280", "B-Efekt a.s."),
+    ("8293", "Mercurius partners s.r.o."),
+    ("8299", "BESTPAY s.r.o."),
+    ("8500", "Multitude Bank p.l.c."),
+];
+
+#[derive(ThisError, Debug, Clone, PartialEq)]
+#[error("Invalid account '{account}': {reason}")]
+pub struct ParseAccountError {
+    account: String,
+    reason: &'static str,
+}
+
+/// Czech bank account number with verified checksums and bank code.
+///
+/// ```
+/// let account: fio_api::CzAccount = "19-2000145399/0800".parse()?;
+/// assert_eq!(Some("Česká spořitelna, a.s."), account.bank_name());
+/// assert_eq!("CZ6508000000192000145399", account.to_iban().as_str());
+/// # Ok::<(), fio_api::ParseAccountError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CzAccount {
+    prefix: u32,
+    number: u64,
+    bank_code: [u8; 4],
+}
+
+impl CzAccount {
+    /// Verifies the checksums and the format of the bank code.
+    pub fn new(prefix: u32, number: u64, bank_code: &str) -> Result<Self, ParseAccountError> {
+        let error = |reason| ParseAccountError {
+            account: format!("{}-{}/{}", prefix, number, bank_code),
+            reason,
+        };
+        if prefix > 999_999 {
+            return Err(error("prefix longer than 6 digits"));
+        }
+        if number > 9_999_999_999 {
+            return Err(error("number longer than 10 digits"));
+        }
+        // at least two non-zero digits
+        if number.to_string().chars().filter(|&c| c != '0').count() < 2 {
+            return Err(error("number too short"));
+        }
+        if !has_valid_checksum(prefix as u64) {
+            return Err(error("invalid checksum of prefix"));
+        }
+        if !has_valid_checksum(number) {
+            return Err(error("invalid checksum of number"));
+        }
+        let bank_code = match bank_code.as_bytes() {
+            code @ &[_, _, _, _] if code.iter().all(u8::is_ascii_digit) => [code[0], code[1], code[2], code[3]],
+            _ => return Err(error("expected 4-digit bank code")),
+        };
+        Ok(Self { prefix, number, bank_code })
+    }
+
+    /// Reads the account from `CZ` IBAN.
+    pub fn from_iban(iban: &Iban) -> Result<Self, ParseAccountError> {
+        let error = |reason| ParseAccountError { account: iban.to_string(), reason };
+        let bban = iban.bban();
+        if iban.country() != "CZ" || !bban.chars().all(|c| c.is_ascii_digit()) {
+            return Err(error("not a CZ IBAN"));
+        }
+        let prefix = bban[4..10].parse().map_err(|_| error("invalid prefix"))?;
+        let number = bban[10..].parse().map_err(|_| error("invalid number"))?;
+        Self::new(prefix, number, &bban[..4])
+    }
+
+    /// Prefix (`předčíslí`); `0` when the account has none.
+    pub fn prefix(&self) -> u32 {
+        self.prefix
+    }
+
+    /// Base number of the account, without prefix.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn bank_code(&self) -> &str {
+        std::str::from_utf8(&self.bank_code).expect("ASCII digits")
+    }
+
+    /// Name of the bank, when the code is in the list of ČNB.
+    pub fn bank_name(&self) -> Option<&'static str> {
+        BANKS.iter()
+            .find(|(code, _)| *code == self.bank_code())
+            .map(|(_, name)| *name)
+    }
+
+    /// Tells if the bank code is in the list of ČNB bundled with this library; a code missing here
+    /// may still belong to a new participant of the payment system.
+    pub fn is_known_bank(&self) -> bool {
+        self.bank_name().is_some()
+    }
+
+    /// The account number without bank code, as used in the `accountTo` field of payment orders.
+    pub fn account_number(&self) -> String {
+        match self.prefix {
+            0 => self.number.to_string(),
+            prefix => format!("{}-{}", prefix, self.number),
+        }
+    }
+
+    pub fn to_iban(&self) -> Iban {
+        let bban = format!("{}{:06}{:010}", self.bank_code(), self.prefix, self.number);
+        Iban::from_bban("CZ", &bban).expect("valid CZ IBAN")
+    }
+}
+
+fn has_valid_checksum(value: u64) -> bool {
+    let sum: u64 = WEIGHTS.iter().rev()
+        .scan(value, |rest, weight| {
+            let digit = *rest % 10;
+            *rest /= 10;
+            Some(digit * weight)
+        })
+        .sum();
+    sum % 11 == 0
+}
+
+impl FromStr for CzAccount {
+    type Err = ParseAccountError;
+
+    /// Accepts `prefix-number/bank` and `number/bank`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseAccountError { account: s.to_string(), reason };
+        let (account, bank_code) = s.trim().split_once('/').ok_or_else(|| error("missing bank code"))?;
+        let (prefix, number) = match account.split_once('-') {
+            Some((prefix, number)) => (prefix, number),
+            None => ("0", account),
+        };
+        let is_digits = |text: &str, max: usize| !text.is_empty() && text.len() <= max && text.chars().all(|c| c.is_ascii_digit());
+        if !is_digits(prefix, 6) || !is_digits(number, 10) || bank_code.len() != 4 {
+            return Err(error("expected format prefix-number/bank"));
+        }
+        Self::new(prefix.parse().map_err(|_| error("invalid prefix"))?,
+                  number.parse().map_err(|_| error("invalid number"))?,
+                  bank_code)
+    }
+}
+
+impl Display for CzAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.account_number(), self.bank_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Iban;
+
+    use super::CzAccount;
+
+    #[test]
+    fn test_parse_and_display() {
+        let account: CzAccount = "19-2000145399/0800".parse().unwrap();
+        assert_eq!(19, account.prefix());
+        assert_eq!(2000145399, account.number());
+        assert_eq!("0800", account.bank_code());
+        assert!(account.is_known_bank());
+        assert_eq!("19-2000145399/0800", account.to_string());
+        let account: CzAccount = "2000145399/0800".parse().unwrap();
+        assert_eq!("2000145399", account.account_number());
+        let account: CzAccount = "2000145399/0801".parse().unwrap();
+        assert!(!account.is_known_bank(), "valid account of a bank missing in the list");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("2000145398/0800".parse::<CzAccount>().is_err(), "checksum of number");
+        assert!("18-2000145399/0800".parse::<CzAccount>().is_err(), "checksum of prefix");
+        assert!("2000145399/08a1".parse::<CzAccount>().is_err(), "bank code format");
+        assert!("2000145399".parse::<CzAccount>().is_err(), "missing bank");
+        assert!("10/0800".parse::<CzAccount>().is_err(), "too short");
+    }
+
+    #[test]
+    fn test_iban() {
+        let account: CzAccount = "19-2000145399/0800".parse().unwrap();
+        assert_eq!("CZ6508000000192000145399", account.to_iban().as_str());
+        assert_eq!(account, CzAccount::from_iban(&"CZ65 0800 0000 1920 0014 5399".parse::<Iban>().unwrap()).unwrap());
+        assert!(CzAccount::from_iban(&"SK3112000000198742637541".parse::<Iban>().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! Exact decimal amount of money.
+//!
+//! Amounts are kept as integer number of hundredths, so that values like `102.93` are never subject to
+//! floating point rounding, neither when parsed from responses nor when written into payment orders.
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+/// Amount with exactly two decimal places.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(i64);
+
+#[derive(ThisError, Debug, Clone, PartialEq)]
+#[error("Invalid amount '{0}'")]
+pub struct ParseAmountError(String);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_hundredths(hundredths: i64) -> Self {
+        Self(hundredths)
+    }
+
+    pub const fn hundredths(&self) -> i64 {
+        self.0
+    }
+
+    pub const fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub const fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub const fn abs(&self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// Formats with comma as decimal separator, as used in Fio CSV.
+    pub fn to_fio_string(&self) -> String {
+        self.to_string().replacen('.', ",", 1)
+    }
+}
+
+impl FromStr for Amount {
+    type Err = ParseAmountError;
+
+    /// Accepts both `.` and `,` as decimal separator, and at most two decimal places;
+    /// `1500.000` is rejected rather than silently truncated.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseAmountError(s.to_string());
+        let text = s.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (units, decimals) = match text.find(['.', ',']) {
+            Some(n) => (&text[..n], &text[n + 1..]),
+            None => (text, ""),
+        };
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if units.is_empty() || !all_digits(units) || !all_digits(decimals) {
+            return Err(error());
+        }
+        if decimals.len() > 2 {
+            return Err(error());
+        }
+        let units: i64 = units.parse().map_err(|_| error())?;
+        let hundredths = decimals.chars()
+            .chain("00".chars())
+            .take(2)
+            .fold(0, |acc, c| acc * 10 + (c as i64 - '0' as i64));
+        let value = units.checked_mul(100)
+            .and_then(|v| v.checked_add(hundredths))
+            .ok_or_else(error)?;
+        Ok(Self(if negative { -value } else { value }))
+    }
+}
+
+impl Display for Amount {
+    /// Always prints two decimal places with `.` as separator, like `102.93` or `-0.50`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Amount {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Amount {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item=Self>>(iter: I) -> Self {
+        iter.fold(Amount::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Amount> for Amount {
+    fn sum<I: Iterator<Item=&'a Amount>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Amount;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(Amount::from_hundredths(10293)), "102.93".parse());
+        assert_eq!(Ok(Amount::from_hundredths(478951)), "4789,51".parse());
+        assert_eq!(Ok(Amount::from_hundredths(-50)), "-0.5".parse());
+        assert_eq!(Ok(Amount::from_hundredths(150000)), "1500".parse());
+        assert!("1500.000".parse::<Amount>().is_err());
+        assert!("1.234".parse::<Amount>().is_err());
+        assert!("abc".parse::<Amount>().is_err());
+        assert!(".5".parse::<Amount>().is_err());
+        assert!("1.2.3".parse::<Amount>().is_err());
+        assert!("".parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("102.93", Amount::from_hundredths(10293).to_string());
+        assert_eq!("-0.50", Amount::from_hundredths(-50).to_string());
+        assert_eq!("0.00", Amount::ZERO.to_string());
+        assert_eq!("4789,51", Amount::from_hundredths(478951).to_fio_string());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let amounts = [Amount::from_hundredths(10), Amount::from_hundredths(20)];
+        assert_eq!(Amount::from_hundredths(30), amounts.iter().sum());
+        assert_eq!(Amount::from_hundredths(-10), amounts[0] - amounts[1]);
+        assert_eq!(Amount::from_hundredths(-10), -amounts[0]);
+    }
+}
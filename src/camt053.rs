@@ -0,0 +1,393 @@
+//! doc/5.1: ISO 20022 `camt.053` (Bank to Customer Statement) format of official reports,
+//! see [crate::ReportFormat::CbaXml] and [crate::ReportFormat::SbaXml].
+//!
+//! A document with more than one `Stmt` element is rejected; namespaces are ignored,
+//! so that all versions of the schema (`camt.053.001.02` and later) are accepted.
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+
+use crate::csvdata::{fio_date, fio_decimal, FioTransactionsRecord, TxType};
+use crate::response::*;
+use crate::xmldata::child;
+use crate::{FioError, FioResponseInfo};
+
+/// Parsed camt.053 statement.
+#[derive(Debug)]
+pub struct Camt053Statement {
+    /// `Stmt/Id`
+    pub id: String,
+    /// `Stmt/ElctrncSeqNb`, or `Stmt/LglSeqNb` if the former is missing
+    pub sequence_number: Option<u32>,
+    pub info: FioResponseInfo,
+    pub entries: Vec<Camt053Entry>,
+}
+
+/// Single `Ntry` element of the statement.
+#[derive(Debug)]
+pub struct Camt053Entry {
+    /// the transaction, in the same representation as in CSV
+    pub record: FioTransactionsRecord,
+    /// `Refs/EndToEndId`
+    pub end_to_end_id: Option<String>,
+    /// `BkTxCd/Domn`, formatted as `domain/family/subfamily`, like `PMNT/RCDT/DMCT`
+    pub bank_transaction_code: Option<String>,
+    /// `BkTxCd/Prtry/Cd`
+    pub proprietary_code: Option<String>,
+    /// all `RmtInf/Ustrd` lines
+    pub remittance_info: Vec<String>,
+}
+
+impl Camt053Statement {
+    /// Parses the complete `Document`.
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let doc = Document::parse(text)?;
+        let report = child(doc.root_element(), "BkToCstmrStmt")
+            .ok_or_else(|| invalid("missing element BkToCstmrStmt"))?;
+        let mut statements = report.children().filter(|n| n.has_tag_name("Stmt"));
+        let stmt = statements.next()
+            .ok_or_else(|| invalid("missing element BkToCstmrStmt/Stmt"))?;
+        if statements.next().is_some() {
+            return Err(invalid("more than one Stmt in the document"));
+        }
+
+        let mut info = FioResponseInfo::from(HashMap::new());
+        if let Some(iban) = path_text(stmt, &["Acct", "Id", "IBAN"]) {
+            info.insert_iban(iban);
+        }
+        if let Some(currency) = path_text(stmt, &["Acct", "Ccy"]) {
+            info.insert(INFO_CURRENCY, currency);
+        }
+        let bic = path_text(stmt, &["Acct", "Svcr", "FinInstnId", "BIC"])
+            .or_else(|| path_text(stmt, &["Acct", "Svcr", "FinInstnId", "BICFI"]));
+        if let Some(bic) = bic {
+            info.insert(INFO_BIC, bic);
+        }
+        let sequence_number = match path_text(stmt, &["ElctrncSeqNb"]).or_else(|| path_text(stmt, &["LglSeqNb"])) {
+            Some(number) => Some(parse_number(number, "ElctrncSeqNb")?),
+            None => None,
+        };
+        if let Some(number) = sequence_number {
+            info.insert(INFO_ID_LIST, number);
+        }
+        if let Some(from) = path_text(stmt, &["FrToDt", "FrDtTm"]) {
+            info.insert(INFO_DATE_START, fio_date::format_fio_date(&fio_date::parse_iso_date(from)?));
+        }
+        if let Some(to) = path_text(stmt, &["FrToDt", "ToDtTm"]) {
+            let date = fio_date::parse_iso_date(to)?;
+            info.insert(INFO_DATE_END, fio_date::format_fio_date(&date));
+            info.insert(INFO_YEAR_LIST, date.format("%Y"));
+        }
+        for balance in stmt.children().filter(|n| n.has_tag_name("Bal")) {
+            let code = path_text(balance, &["Tp", "CdOrPrtry", "Cd"]).unwrap_or("");
+            let (amount_key, date_key) = match code {
+                "OPBD" | "PRCD" => (INFO_OPENING_BALANCE, INFO_DATE_START),
+                "CLBD" => (INFO_CLOSING_BALANCE, INFO_DATE_END),
+                _ => continue,
+            };
+            info.insert(amount_key, signed_amount(balance)?);
+            if !info.get_ref().contains_key(date_key) {
+                if let Some(date) = path_text(balance, &["Dt", "Dt"]) {
+                    info.insert(date_key, fio_date::format_fio_date(&fio_date::parse_iso_date(date)?));
+                }
+            }
+        }
+
+        let currency = info.currency().map(str::to_string).unwrap_or_default();
+        let entries = stmt.children()
+            .filter(|n| n.has_tag_name("Ntry"))
+            .map(|entry| parse_entry(entry, &currency))
+            .collect::<crate::Result<_>>()?;
+        Ok(Self {
+            id: path_text(stmt, &["Id"]).unwrap_or("").to_string(),
+            sequence_number,
+            info,
+            entries,
+        })
+    }
+
+    /// Returns just the transaction records, dropping camt-specific details.
+    pub fn into_records(self) -> Vec<FioTransactionsRecord> {
+        self.entries.into_iter()
+            .map(|entry| entry.record)
+            .collect()
+    }
+}
+
+fn invalid(message: &str) -> FioError {
+    FioError::InvalidData(format!("camt.053: {}", message))
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str, field: &str) -> crate::Result<T> {
+    text.parse().map_err(|_| invalid(&format!("{} is not a number: '{}'", field, text)))
+}
+
+fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |node, name| child(node, name))
+}
+
+fn path_text<'a>(node: Node<'a, '_>, names: &[&str]) -> Option<&'a str> {
+    path(node, names)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+/// Reads `Amt` and applies the sign of `CdtDbtInd`.
+fn signed_amount(node: Node) -> crate::Result<String> {
+    let amount = path_text(node, &["Amt"]).ok_or_else(|| invalid("missing Amt"))?;
+    match path_text(node, &["CdtDbtInd"]) {
+        Some("DBIT") => Ok(format!("-{}", amount)),
+        _ => Ok(amount.to_string()),
+    }
+}
+
+fn parse_entry(entry: Node, currency: &str) -> crate::Result<Camt053Entry> {
+    let id_tx = match path_text(entry, &["AcctSvcrRef"]) {
+        Some(reference) => parse_number(reference, "AcctSvcrRef")?,
+        None => {
+            let reference = path_text(entry, &["NtryRef"]).ok_or_else(|| invalid("missing entry reference"))?;
+            parse_number(reference, "NtryRef")?
+        }
+    };
+    let date = path_text(entry, &["BookgDt", "Dt"])
+        .or_else(|| path_text(entry, &["BookgDt", "DtTm"]))
+        .or_else(|| path_text(entry, &["ValDt", "Dt"]))
+        .ok_or_else(|| invalid("missing booking date"))?;
+    let date: NaiveDate = fio_date::parse_iso_date(date)?;
+    let value = fio_decimal::parse_fio_decimal(&signed_amount(entry)?)?;
+    let currency = path(entry, &["Amt"])
+        .and_then(|n| n.attribute("Ccy"))
+        .unwrap_or(currency);
+    let credit = path_text(entry, &["CdtDbtInd"]) != Some("DBIT");
+
+    let domain = path(entry, &["BkTxCd", "Domn"]);
+    let bank_transaction_code = domain.map(|domain| {
+        [
+            path_text(domain, &["Cd"]),
+            path_text(domain, &["Fmly", "Cd"]),
+            path_text(domain, &["Fmly", "SubFmlyCd"]),
+        ].iter().flatten().copied().collect::<Vec<_>>().join("/")
+    });
+    let proprietary_code = path_text(entry, &["BkTxCd", "Prtry", "Cd"]).map(str::to_string);
+
+    let details = path(entry, &["NtryDtls", "TxDtls"]);
+    let detail = |names: &[&str]| details.and_then(|d| path_text(d, names)).unwrap_or("");
+    let end_to_end_id = Some(detail(&["Refs", "EndToEndId"]))
+        .filter(|s| !s.is_empty() && *s != "NOTPROVIDED")
+        .map(str::to_string);
+    let remittance_info: Vec<String> = details
+        .and_then(|d| child(d, "RmtInf"))
+        .map(|rmt| rmt.children()
+            .filter(|n| n.has_tag_name("Ustrd"))
+            .filter_map(|n| n.text())
+            .map(|s| s.trim().to_string())
+            .collect())
+        .unwrap_or_default();
+    // the counterparty is the debtor for incoming payments, creditor for outgoing
+    let (party, party_account, party_agent) = if credit {
+        ("Dbtr", "DbtrAcct", "DbtrAgt")
+    } else {
+        ("Cdtr", "CdtrAcct", "CdtrAgt")
+    };
+    let b_account = Some(detail(&["RltdPties", party_account, "Id", "Othr", "Id"]))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| detail(&["RltdPties", party_account, "Id", "IBAN"]));
+    let b_bankid = Some(detail(&["RltdAgts", party_agent, "FinInstnId", "Othr", "Id"]))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| detail(&["RltdAgts", party_agent, "FinInstnId", "ClrSysMmbId", "MmbId"]));
+    let bic = Some(detail(&["RltdAgts", party_agent, "FinInstnId", "BIC"]))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| detail(&["RltdAgts", party_agent, "FinInstnId", "BICFI"]));
+    let b_bank_name = Some(detail(&["RltdAgts", party_agent, "FinInstnId", "Nm"]))
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    let symbol = |name: &str| end_to_end_id.as_deref()
+        .and_then(|id| parse_symbol(id, name))
+        .unwrap_or_default();
+    let id_command = match detail(&["Refs", "InstrId"]) {
+        "" => None,
+        s => s.parse().ok(),
+    };
+
+    let record = FioTransactionsRecord {
+        id_tx,
+        date,
+        value,
+        currency: currency.to_string(),
+        b_account: b_account.to_string(),
+        b_account_name: detail(&["RltdPties", party, "Nm"]).to_string(),
+        b_bankid: b_bankid.to_string(),
+        b_bank_name,
+        ks: symbol("KS"),
+        vs: symbol("VS"),
+        ss: symbol("SS"),
+        custom_id: String::new(),
+        message: remittance_info.join(" "),
+        tx_type: TxType::parse(path_text(entry, &["AddtlNtryInf"]).unwrap_or("").to_string()),
+        who: String::new(),
+        note: String::new(),
+        comment: String::new(),
+        bic: bic.to_string(),
+        id_command,
+    };
+    Ok(Camt053Entry {
+        record,
+        end_to_end_id,
+        bank_transaction_code,
+        proprietary_code,
+        remittance_info,
+    })
+}
+
+/// Czech banks pass payment symbols in end-to-end id, like `/VS123/SS456/KS0308`.
+fn parse_symbol(end_to_end_id: &str, name: &str) -> Option<String> {
+    end_to_end_id.split('/')
+        .find_map(|part| part.strip_prefix(name))
+        .filter(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::csvdata::TxType;
+    use crate::Amount;
+    use crate::error::Result;
+    use crate::StatementParser;
+
+    use super::Camt053Statement;
+
+    const SAMPLE1: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>2345678901-2021-6</MsgId><CreDtTm>2021-07-01T08:00:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>2345678901-2021-6</Id>
+      <ElctrncSeqNb>6</ElctrncSeqNb>
+      <LglSeqNb>6</LglSeqNb>
+      <FrToDt><FrDtTm>2021-06-01T00:00:00.0+02:00</FrDtTm><ToDtTm>2021-06-30T23:59:59.9+02:00</ToDtTm></FrToDt>
+      <Acct>
+        <Id><IBAN>CZ6220100000002345678901</IBAN></Id>
+        <Ccy>CZK</Ccy>
+        <Svcr><FinInstnId><BIC>FIOBCZPPXXX</BIC></FinInstnId></Svcr>
+      </Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="CZK">4789.51</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2021-05-31</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="CZK">6186.58</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2021-06-30</Dt></Dt>
+      </Bal>
+      <Ntry>
+        <NtryRef>23771345451</NtryRef>
+        <Amt Ccy="CZK">1500.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2021-06-01</Dt></BookgDt>
+        <ValDt><Dt>2021-06-01</Dt></ValDt>
+        <AcctSvcrRef>23771345451</AcctSvcrRef>
+        <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>RCDT</Cd><SubFmlyCd>DMCT</SubFmlyCd></Fmly></Domn></BkTxCd>
+        <NtryDtls><TxDtls>
+          <Refs><InstrId>26010123456</InstrId><EndToEndId>/VS123/SS/KS0308</EndToEndId></Refs>
+          <RltdPties>
+            <Dbtr><Nm>Novák, Jan</Nm></Dbtr>
+            <DbtrAcct><Id><Othr><Id>2702016516</Id></Othr></Id></DbtrAcct>
+          </RltdPties>
+          <RltdAgts><DbtrAgt><FinInstnId><BIC>FIOBCZPPXXX</BIC><Othr><Id>2010</Id></Othr></FinInstnId></DbtrAgt></RltdAgts>
+          <RmtInf><Ustrd>nájem</Ustrd><Ustrd>červen</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+        <AddtlNtryInf>Příjem převodem uvnitř banky</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <NtryRef>23794028126</NtryRef>
+        <Amt Ccy="CZK">102.93</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2021-06-30</Dt></BookgDt>
+        <BkTxCd><Prtry><Cd>CARD</Cd></Prtry></BkTxCd>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+          <RltdPties><Cdtr><Nm>Shop</Nm></Cdtr><CdtrAcct><Id><IBAN>DE89370400440532013000</IBAN></Id></CdtrAcct></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+    #[test]
+    fn test_parse_camt053() -> Result<()> {
+        let statement = Camt053Statement::parse(SAMPLE1)?;
+        assert_eq!("2345678901-2021-6", statement.id);
+        assert_eq!(Some(6), statement.sequence_number);
+        let info = &statement.info;
+        assert_eq!("CZ6220100000002345678901", info.iban()?);
+        assert_eq!("2345678901", info.account_id()?);
+        assert_eq!("2010", info.bank_id()?);
+        assert_eq!("FIOBCZPPXXX", info.bic()?);
+        assert_eq!(Amount::from_hundredths(478951), info.opening_balance()?);
+        assert_eq!(Amount::from_hundredths(618658), info.closing_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), info.date_start()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), info.date_end()?);
+
+        assert_eq!(2, statement.entries.len());
+        let entry = &statement.entries[0];
+        assert_eq!(Some("/VS123/SS/KS0308".to_string()), entry.end_to_end_id);
+        assert_eq!(Some("PMNT/RCDT/DMCT".to_string()), entry.bank_transaction_code);
+        assert_eq!(vec!["nájem", "červen"], entry.remittance_info);
+        let tx = &entry.record;
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!("2702016516", tx.b_account);
+        assert_eq!("2010", tx.b_bankid);
+        assert_eq!("Novák, Jan", tx.b_account_name);
+        assert_eq!("123", tx.vs);
+        assert_eq!("0308", tx.ks);
+        assert_eq!("", tx.ss);
+        assert_eq!("nájem červen", tx.message);
+        assert!(matches!(tx.tx_type, TxType::FioIncome));
+        assert_eq!(Some(26010123456), tx.id_command);
+
+        let entry = &statement.entries[1];
+        assert_eq!(None, entry.end_to_end_id);
+        assert_eq!(None, entry.bank_transaction_code);
+        assert_eq!(Some("CARD".to_string()), entry.proprietary_code);
+        assert_eq!(Amount::from_hundredths(-10293), entry.record.value);
+        assert_eq!("DE89370400440532013000", entry.record.b_account);
+        assert_eq!("Shop", entry.record.b_account_name);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_statement() {
+        assert!(Camt053Statement::parse("<Document><BkToCstmrStmt/></Document>").is_err());
+    }
+
+    #[test]
+    fn test_multiple_statements() {
+        let start = SAMPLE1.find("<Stmt>").unwrap();
+        let end = SAMPLE1.find("</Stmt>").unwrap() + "</Stmt>".len();
+        let text = SAMPLE1.replace("</Stmt>", &format!("</Stmt>{}", &SAMPLE1[start..end]));
+        assert!(matches!(Camt053Statement::parse(&text), Err(crate::FioError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_invalid_reference() {
+        let text = SAMPLE1.replace("<AcctSvcrRef>23771345451</AcctSvcrRef>", "<AcctSvcrRef>X-1</AcctSvcrRef>");
+        match Camt053Statement::parse(&text) {
+            Err(crate::FioError::InvalidData(message)) => assert!(message.contains("AcctSvcrRef"), "{}", message),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut bytes = SAMPLE1.as_bytes().to_vec();
+        bytes[50] = 0xff;
+        assert!(matches!(crate::ReportFormat::CbaXml.parse_statement(&bytes), Err(crate::FioError::Utf8Error(_))));
+    }
+}
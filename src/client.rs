@@ -1,66 +1,291 @@
-use std::cell::Cell;
+use std::future::Future;
+use std::sync::Arc;
 
+use chrono::NaiveDate;
 use reqwest::{Response, StatusCode, Version};
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
 use reqwest::multipart::{Form, Part};
-use tokio::time::Duration;
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
 
-use crate::{DomesticPayment, FioExportReq, ForeignPayment, ForeignTransaction, T2Payment};
+use crate::{DomesticPayment, FioError, FioExportReq, FioPeriod, FioResponse, ForeignPayment, ForeignTransaction, ImportResult, ReportFormat, Statement, T2Payment, TxFormat};
+use crate::error::error_from_response;
 use crate::import::{DetailsOfCharges, PaymentBuilder, ToPaymentXml};
+use crate::rate_limit::{MemoryRateLimitStore, RateLimitStore, RateLimiter};
+use crate::RetryPolicy;
 
-pub(crate) const FIOAPI_URL_BASE: &'static str = "https://www.fio.cz/ib_api/rest";
-const REQUEST_RATE: Duration = Duration::from_secs(30);
+pub const FIOAPI_URL_BASE: &str = "https://www.fio.cz/ib_api/rest";
+pub(crate) const REQUEST_RATE: Duration = Duration::from_secs(30);
+const DEFAULT_USER_AGENT: &str = "fio-api-rs";
 
 /// The low-level client that holds the token.
+///
+/// It is `Send + Sync`, so one instance (typically in an `Arc`) can be shared by multiple tasks;
+/// their requests are then queued to respect the request rate of the token.
 pub struct FioClient {
-    token: String,
-    last_request: Cell<Instant>,
+    pub(crate) token: String,
+    url_base: String,
+    limiter: RateLimiter,
+    retry_policy: RetryPolicy,
     client: reqwest::Client,
 }
 
+/// Configuration of [FioClient]; use it when the defaults of [FioClient::new] do not fit.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// let fio = fio_api::FioClient::builder("token")
+///     .url_base("http://localhost:8080/ib_api/rest")
+///     .timeout(Duration::from_secs(10))
+///     .build()?;
+/// # Ok::<(), fio_api::FioError>(())
+/// ```
+pub struct FioClientBuilder {
+    token: String,
+    url_base: String,
+    user_agent: String,
+    request_rate: Duration,
+    rate_limit_store: Option<Arc<dyn RateLimitStore>>,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<reqwest::Proxy>,
+}
+
+impl FioClientBuilder {
+    fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            url_base: FIOAPI_URL_BASE.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            request_rate: REQUEST_RATE,
+            rate_limit_store: None,
+            retry_policy: RetryPolicy::default(),
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+        }
+    }
+
+    /// Base URL of the API, without trailing slash; defaults to [FIOAPI_URL_BASE].
+    pub fn url_base(mut self, url_base: &str) -> Self {
+        self.url_base = url_base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Value of `User-Agent` header; defaults to `fio-api-rs`.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Minimal interval between two requests with the same token; Fio API requires 30 seconds (doc/8.3).
+    /// Only useful to change when talking to a stand-in server.
+    pub fn request_rate(mut self, request_rate: Duration) -> Self {
+        self.request_rate = request_rate;
+        self
+    }
+
+    /// Where to record the use of the token; share the store between clients using the same token.
+    /// Defaults to a new [MemoryRateLimitStore] of this client.
+    pub fn rate_limit_store(mut self, store: Arc<dyn RateLimitStore>) -> Self {
+        self.rate_limit_store = Some(store);
+        self
+    }
+
+    /// How to repeat failed requests; see [RetryPolicy] for the details.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Timeout of the whole request, including reading the response body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of the connect phase.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Routes all requests through given proxy.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn build(self) -> crate::Result<FioClient> {
+        let mut headers = HeaderMap::new();
+        let user_agent = HeaderValue::from_str(&self.user_agent)
+            .map_err(|_| FioError::InvalidConfig(format!("Invalid user agent '{}'", self.user_agent)))?;
+        headers.insert(USER_AGENT, user_agent);
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        let store = self.rate_limit_store
+            .unwrap_or_else(|| Arc::new(MemoryRateLimitStore::new()));
+        Ok(FioClient {
+            limiter: RateLimiter::new(self.request_rate, store, &self.token),
+            token: self.token,
+            url_base: self.url_base,
+            retry_policy: self.retry_policy,
+            client: builder.build()?,
+        })
+    }
+}
+
 pub struct FioClientWithImport {
-    fio: FioClient,
+    pub(crate) fio: FioClient,
     account_from: String,
     currency: String,
 }
 
 impl FioClient {
+    /// Client with default configuration, talking to the real Fio API.
+    ///
+    /// Panics only when the HTTP client cannot be initialized at all (e.g. no TLS backend);
+    /// use [Self::builder] to get such failure as [FioError].
     pub fn new(token: &str) -> Self {
-        let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, HeaderValue::from_static("fio-api-rs"));
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build().unwrap();
-        Self {
-            token: token.to_string(),
-            last_request: Cell::new(Instant::now() - REQUEST_RATE),
-            client,
-        }
+        Self::builder(token)
+            .build()
+            .expect("default configuration of HTTP client")
+    }
+
+    pub fn builder(token: &str) -> FioClientBuilder {
+        FioClientBuilder::new(token)
     }
 
     /// Read-only commands.
     /// See methods in [FioExportReq] for commands that can be used here.
-    pub async fn export(&self, fio_req: FioExportReq) -> reqwest::Result<Response> {
+    ///
+    /// Failed requests are repeated according to the [RetryPolicy] of the client;
+    /// the error of the last attempt is returned when the policy does not allow more.
+    pub async fn export(&self, fio_req: FioExportReq) -> crate::Result<Response> {
+        let slot = self.reserve_slot()?;
+        self.execute_export(&fio_req, slot).await
+    }
+
+    /// Like [Self::export], but fails with [FioError::Cancelled] as soon as `cancel` completes.
+    ///
+    /// Typically used to stop waiting for the rate limit, e.g. on shutdown:
+    /// ```no_run
+    /// # async fn f(fio: fio_api::FioClient, shutdown: tokio::sync::oneshot::Receiver<()>) {
+    /// let req = fio_api::FioExportReq::Last { format: fio_api::TxFormat::Json };
+    /// let result = fio.export_cancellable(req, async { shutdown.await.ok(); }).await;
+    /// # }
+    /// ```
+    /// The slot reserved for the request stays reserved, so a cancelled call still delays the following ones.
+    pub async fn export_cancellable<C: Future<Output=()>>(&self, fio_req: FioExportReq, cancel: C) -> crate::Result<Response> {
+        tokio::select! {
+            result = self.export(fio_req) => result,
+            _ = cancel => Err(FioError::Cancelled),
+        }
+    }
+
+    /// Downloads the data and parses them with the parser matching the requested format.
+    pub async fn export_statement(&self, fio_req: FioExportReq) -> crate::Result<Statement> {
+        let parser = fio_req.statement_parser()
+            .ok_or_else(|| FioError::UnsupportedFormat(Into::<&str>::into(&fio_req).to_string()))?;
+        let slot = self.reserve_slot()?;
+        let response = self.execute_export(&fio_req, slot).await?;
+        FioResponse::try_from(response).await?
+            .statement(parser)
+    }
+
+    /// doc/5.2.1: Transactions in the given period, both days inclusive.
+    pub async fn transactions_between(&self, date_start: NaiveDate, date_end: NaiveDate) -> crate::Result<Statement> {
+        self.export_statement(FioExportReq::Periods { date_start, date_end, format: TxFormat::Csv }).await
+    }
+
+    /// doc/5.2.2: Official statement, identified by year and its number within the year.
+    pub async fn statement(&self, year: u16, id: u8) -> crate::Result<Statement> {
+        self.export_statement(FioExportReq::ById { year, id, format: ReportFormat::Csv }).await
+    }
+
+    /// doc/5.2.3: Transactions since the last download; the bank moves the mark past them,
+    /// so each transaction is returned only once. See [FioExportReq::SetLastId] to move the mark back.
+    pub async fn new_transactions(&self) -> crate::Result<Statement> {
+        self.export_statement(FioExportReq::Last { format: TxFormat::Csv }).await
+    }
+
+    /// doc/5.2.5: Card transactions of merchant in the given period, both days inclusive.
+    pub async fn merchant_transactions(&self, date_start: NaiveDate, date_end: NaiveDate) -> crate::Result<Statement> {
+        self.export_statement(FioExportReq::Merchant { date_start, date_end, format: TxFormat::Csv }).await
+    }
+
+    /// doc/5.2.6: Year and number of the last official statement, or `None` if there is no statement yet.
+    /// For monthly statements, the number is the month, see [FioPeriod].
+    pub async fn last_statement(&self) -> crate::Result<Option<FioPeriod>> {
+        let text = self.export(FioExportReq::LastStatement).await?
+            .text().await?;
+        match text.trim() {
+            "" => Ok(None),
+            text => Ok(Some(FioPeriod::parse(text)?)),
+        }
+    }
+
+    /// Reserves the slot for the first attempt of a request, unless it is beyond the `max_wait` of the [RetryPolicy];
+    /// a request that is never sent does not delay the following ones.
+    pub(crate) fn reserve_slot(&self) -> crate::Result<Instant> {
+        let started = Instant::now();
+        self.limiter.reserve_if(|slot| !self.retry_policy.is_too_late(started, slot))
+            .ok_or(FioError::InvalidTiming)
+    }
+
+    /// Sends the request in the given slot, previously obtained from [Self::reserve_slot], and repeats it
+    /// according to the [RetryPolicy]; only successful responses are returned.
+    pub(crate) async fn execute_export(&self, fio_req: &FioExportReq, mut slot: Instant) -> crate::Result<Response> {
+        let policy = &self.retry_policy;
+        let started = Instant::now();
+        let mut attempts = 0;
         loop {
-            let next_time = self.last_request.get() + REQUEST_RATE;
-            let now = Instant::now();
-            if now < next_time {
-                log::trace!("Delaying next call to FIO API; duration {}", next_time.duration_since(now).as_millis());
-                tokio::time::sleep_until(next_time).await;
-                self.last_request.set(next_time);
-            }
-            log::trace!("Trying '{}'", fio_req.build_url("__CENSORED__"));
+            RateLimiter::wait_for(slot).await;
+            attempts += 1;
+            log::trace!("Trying '{}'", fio_req.build_url(&self.url_base, "__CENSORED__"));
             let http_request = self.client
-                .get(fio_req.build_url(&self.token))
+                .get(fio_req.build_url(&self.url_base, &self.token))
                 .build()?;
-            let response = self.client.execute(http_request).await?;
-            match response.status() {
-                StatusCode::CONFLICT => self.last_request.set(Instant::now()),
-                _ => return response.error_for_status()
+            let error = match self.client.execute(http_request).await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if response.status() == StatusCode::CONFLICT => {
+                    self.limiter.postpone();
+                    FioError::InvalidTiming
+                }
+                Ok(response) => error_from_response(response).await,
+                Err(e) => FioError::ReqwestError(e),
+            };
+            if !error.is_retryable() || !policy.can_retry(attempts) {
+                return Err(error);
             }
+            let not_before = match error {
+                FioError::InvalidTiming => Instant::now(),
+                _ => match policy.retry_at(attempts) {
+                    Some(not_before) => not_before,
+                    // the backoff is beyond any deadline
+                    None => return Err(error),
+                },
+            };
+            slot = match self.limiter.reserve_if(|slot| !policy.is_too_late(started, slot.max(not_before))) {
+                Some(slot) => slot.max(not_before),
+                None => return Err(error),
+            };
+            log::debug!("Repeating request after error: {}", error);
         }
     }
+
+    pub(crate) fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
 }
 
 impl FioClientWithImport {
@@ -74,10 +299,55 @@ impl FioClientWithImport {
         }
     }
 
+    /// The underlying client, for exports with the same token.
+    pub fn fio(&self) -> &FioClient {
+        &self.fio
+    }
+
     /// doc/6.1 Import commands - like payments.
-    pub async fn import<P: ToPaymentXml>(&self, payment: P) -> reqwest::Result<Response> {
-        let payment_xml = payment.to_payment_xml().unwrap();
+    ///
+    /// The request shares the rate limit with exports of the same token; when refused with `409 Conflict`,
+    /// it is repeated according to the [RetryPolicy] of the client, failing with [FioError::InvalidTiming] at last.
+    ///
+    /// The batch can be rejected as a whole, or just some of its orders; see [ImportResult::failed_orders].
+    pub async fn import<P: ToPaymentXml>(&self, payment: P) -> crate::Result<ImportResult> {
+        let payment_xml = payment.to_payment_xml()?;
+        let slot = self.fio.reserve_slot()?;
+        self.execute_import(&payment_xml, slot).await
+    }
+
+    /// Sends the batch in the given slot, previously obtained from [FioClient::reserve_slot],
+    /// and repeats it according to the [RetryPolicy] while refused with `409 Conflict`.
+    pub(crate) async fn execute_import(&self, payment_xml: &str, mut slot: Instant) -> crate::Result<ImportResult> {
         log::trace!("payment_xml:\n{}", payment_xml);
+        let policy = &self.fio.retry_policy;
+        let started = Instant::now();
+        let mut attempts = 0;
+        let response = loop {
+            RateLimiter::wait_for(slot).await;
+            attempts += 1;
+            let http_request = self.build_import_request(payment_xml)?;
+            log::trace!("HTTP Request: {:?}", http_request);
+            let response = self.fio.client.execute(http_request).await?;
+            match response.status() {
+                StatusCode::CONFLICT => {
+                    self.fio.limiter.postpone();
+                    if !policy.can_retry(attempts) {
+                        return Err(FioError::InvalidTiming);
+                    }
+                    slot = self.fio.limiter.reserve_if(|slot| !policy.is_too_late(started, slot))
+                        .ok_or(FioError::InvalidTiming)?;
+                }
+                status if status.is_success() => break response,
+                _ => return Err(error_from_response(response).await),
+            }
+        };
+        let text = response.text().await?;
+        log::trace!("import response:\n{}", text);
+        ImportResult::parse(&text)
+    }
+
+    fn build_import_request(&self, payment_xml: &str) -> reqwest::Result<reqwest::Request> {
         let part = Part::text(payment_xml.to_string())
             .file_name("payments.xml")
             .mime_str("application/xml")?;
@@ -86,36 +356,11 @@ impl FioClientWithImport {
             // .text("lng", "en")
             .text("token", self.fio.token.to_string())
             .part("file", part);
-        let http_request = self.fio.client
-            .post(format!("{url_base}/import/", url_base = FIOAPI_URL_BASE))
+        self.fio.client
+            .post(format!("{url_base}/import/", url_base = self.fio.url_base))
             .version(Version::HTTP_11)
             .multipart(form)
-            .build()?;
-        log::trace!("HTTP Request: {:?}", http_request);
-        let response = self.fio.client.execute(http_request).await?;
-        response.error_for_status()
-        //TODO process response
-        // like this:
-        // ```
-        // <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
-        // <responseImport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://www.fio.cz/schema/responseImport.xsd">
-        // <result>
-        //   <errorCode>0</errorCode>
-        //   <idInstruction>1801400777</idInstruction>
-        //   <status>ok</status>
-        //   <sums>
-        //    <sum id="CZK">
-        //     <sumCredit>0</sumCredit>
-        //     <sumDebet>444.90</sumDebet>
-        //    </sum>
-        //   </sums>
-        // </result>
-        // <ordersDetails>
-        //  <detail id="1"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
-        //  <detail id="2"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
-        // </ordersDetails>
-        // </responseImport>
-        // ```
+            .build()
     }
 
     /// Create a domestic transaction with account info pre-filled.
@@ -138,3 +383,70 @@ impl FioClientWithImport {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::{Duration, Instant};
+
+    use crate::{FioError, FioExportReq, RetryPolicy, TxFormat};
+
+    use super::FioClient;
+
+    /// Nothing listens there, so every request fails with a connection error.
+    const CLOSED_URL_BASE: &str = "http://127.0.0.1:1/ib_api/rest";
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FioClient>();
+    }
+
+    #[test]
+    fn test_builder_url_base() -> crate::Result<()> {
+        let fio = FioClient::builder("TOKEN")
+            .url_base("http://localhost:8080/ib_api/rest/")
+            .user_agent("test")
+            .build()?;
+        let req = FioExportReq::Last { format: TxFormat::Json };
+        assert_eq!("http://localhost:8080/ib_api/rest/last/TOKEN/transactions.json", req.build_url(&fio.url_base, &fio.token));
+        assert!(matches!(FioClient::builder("TOKEN").user_agent("bad\n").build(), Err(FioError::InvalidConfig(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_error_backoff() -> crate::Result<()> {
+        let fio = FioClient::builder("TOKEN")
+            .url_base(CLOSED_URL_BASE)
+            .request_rate(Duration::ZERO)
+            .retry_policy(RetryPolicy::default().max_attempts(3).backoff(Duration::from_millis(20)))
+            .build()?;
+        let start = Instant::now();
+        let result = fio.export(FioExportReq::Last { format: TxFormat::Json }).await;
+        assert!(matches!(result, Err(FioError::ReqwestError(e)) if e.is_connect()));
+        // 20ms after the first attempt, 40ms after the second
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_and_cancel() -> crate::Result<()> {
+        let fio = FioClient::builder("TOKEN")
+            .url_base(CLOSED_URL_BASE)
+            .retry_policy(RetryPolicy::default().max_attempts(1))
+            .build()?;
+        assert!(fio.export(FioExportReq::Last { format: TxFormat::Json }).await.is_err());
+        // the next slot is 30 seconds away
+        let cancel = tokio::time::sleep(Duration::from_millis(10));
+        assert!(matches!(fio.export_cancellable(FioExportReq::Last { format: TxFormat::Json }, cancel).await, Err(FioError::Cancelled)));
+        let fio = FioClient::builder("TOKEN")
+            .url_base(CLOSED_URL_BASE)
+            .retry_policy(RetryPolicy::default().max_wait(Duration::from_secs(1)))
+            .build()?;
+        fio.limiter().reserve();
+        let next_slot = fio.limiter().next_slot();
+        assert!(matches!(fio.export(FioExportReq::Last { format: TxFormat::Json }).await, Err(FioError::InvalidTiming)));
+        let refused_slot = fio.limiter().next_slot();
+        assert!(refused_slot < next_slot + Duration::from_secs(1), "the refused call must not reserve a slot");
+        Ok(())
+    }
+}
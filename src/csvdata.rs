@@ -1,52 +1,232 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use chrono::NaiveDate;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumString;
 use strum_macros::IntoStaticStr;
 
-#[derive(Debug, Deserialize)]
+use crate::{Amount, Bic, CzAccount, Iban};
+
+/// Single transaction (pohyb na účtu), as found in CSV export.
+/// Other formats are converted to this representation too, see [crate::Statement].
+///
+/// Serialization uses the same column names and value formats as Fio CSV.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FioTransactionsRecord {
     #[serde(rename = "ID pohybu")]
-    id_tx: u64,
+    pub(crate) id_tx: u64,
     #[serde(rename = "Datum", with = "fio_date")]
-    date: NaiveDate,
+    pub(crate) date: NaiveDate,
     #[serde(rename = "Objem", with = "fio_decimal")]
-    value: f64,
+    pub(crate) value: Amount,
     #[serde(rename = "Měna")]
-    currency: String,
+    pub(crate) currency: String,
     #[serde(rename="Protiúčet")]
-    b_account: String,
+    pub(crate) b_account: String,
     #[serde(rename="Název protiúčtu")]
-    b_account_name: String,
+    pub(crate) b_account_name: String,
     #[serde(rename="Kód banky")]
-    b_bankid: String,
+    pub(crate) b_bankid: String,
     #[serde(rename="Název banky")]
-    b_bank_name: Option<String>,
+    pub(crate) b_bank_name: Option<String>,
     #[serde(rename="KS")]
-    ks: String,
+    pub(crate) ks: String,
     #[serde(rename="VS")]
-    vs: String,
+    pub(crate) vs: String,
     #[serde(rename="SS")]
-    ss: String,
+    pub(crate) ss: String,
     #[serde(rename="Uživatelská identifikace")]
-    custom_id: String,
+    pub(crate) custom_id: String,
     #[serde(rename="Zpráva pro příjemce")]
-    message: String,
+    pub(crate) message: String,
     #[serde(rename="Typ", with="fio_txtype")]
-    tx_type: TxType,
+    pub(crate) tx_type: TxType,
     #[serde(rename="Provedl")]
-    who: String,
+    pub(crate) who: String,
     #[serde(rename="Upřesnění")]
-    note: String,
+    pub(crate) note: String,
     #[serde(rename="Komentář")]
-    comment: String,
+    pub(crate) comment: String,
     #[serde(rename="BIC")]
-    bic: String,
+    pub(crate) bic: String,
     #[serde(rename="ID pokynu")]
-    id_command: u64,
+    pub(crate) id_command: Option<u64>,
+}
+
+/// 5.1 Podporované formáty dat / Identifikace sloupců (`columnN` v JSON, `column_N` v XML)
+pub(crate) mod column {
+    pub const DATE: u16 = 0;
+    pub const VALUE: u16 = 1;
+    pub const B_ACCOUNT: u16 = 2;
+    pub const B_BANKID: u16 = 3;
+    pub const KS: u16 = 4;
+    pub const VS: u16 = 5;
+    pub const SS: u16 = 6;
+    pub const CUSTOM_ID: u16 = 7;
+    pub const TX_TYPE: u16 = 8;
+    pub const WHO: u16 = 9;
+    pub const B_ACCOUNT_NAME: u16 = 10;
+    pub const B_BANK_NAME: u16 = 12;
+    pub const CURRENCY: u16 = 14;
+    pub const MESSAGE: u16 = 16;
+    pub const ID_COMMAND: u16 = 17;
+    pub const NOTE: u16 = 18;
+    pub const ID_TX: u16 = 22;
+    pub const COMMENT: u16 = 25;
+    pub const BIC: u16 = 26;
+}
+
+impl FioTransactionsRecord {
+    /// Builds the record from column values identified by their numeric id, as used by JSON and XML formats.
+    /// Missing columns are treated as empty values; dates are expected in ISO format (`2021-06-30+0200`).
+    pub(crate) fn from_columns(columns: &HashMap<u16, String>) -> crate::Result<Self> {
+        let text = |id: u16| columns.get(&id).cloned().unwrap_or_default();
+        let required = |id: u16| columns.get(&id)
+            .map(|s| s.as_str())
+            .ok_or_else(|| crate::FioError::MissingColumn(id));
+        let id_command = match columns.get(&column::ID_COMMAND) {
+            Some(s) if !s.is_empty() => Some(s.parse()?),
+            _ => None,
+        };
+        Ok(Self {
+            id_tx: required(column::ID_TX)?.parse()?,
+            date: fio_date::parse_iso_date(required(column::DATE)?)?,
+            value: fio_decimal::parse_fio_decimal(required(column::VALUE)?)?,
+            currency: text(column::CURRENCY),
+            b_account: text(column::B_ACCOUNT),
+            b_account_name: text(column::B_ACCOUNT_NAME),
+            b_bankid: text(column::B_BANKID),
+            b_bank_name: columns.get(&column::B_BANK_NAME).cloned(),
+            ks: text(column::KS),
+            vs: text(column::VS),
+            ss: text(column::SS),
+            custom_id: text(column::CUSTOM_ID),
+            message: text(column::MESSAGE),
+            tx_type: TxType::parse(text(column::TX_TYPE)),
+            who: text(column::WHO),
+            note: text(column::NOTE),
+            comment: text(column::COMMENT),
+            bic: text(column::BIC),
+            id_command,
+        })
+    }
+}
+
+impl FioTransactionsRecord {
+    /// ID pohybu: unique id of the transaction
+    pub fn id(&self) -> u64 {
+        self.id_tx
+    }
+
+    /// Datum: date of the transaction
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Objem: amount; negative for outgoing payments
+    pub fn amount(&self) -> Amount {
+        self.value
+    }
+
+    /// Měna: currency according to ISO 4217
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Protiúčet: counter-account number, or IBAN for foreign payments
+    pub fn counter_account(&self) -> &str {
+        &self.b_account
+    }
+
+    /// Counter-account as [CzAccount]; `None` for foreign accounts, or when it cannot be verified.
+    pub fn counter_account_cz(&self) -> Option<CzAccount> {
+        format!("{}/{}", self.b_account, self.b_bankid).parse().ok()
+    }
+
+    /// Counter-account as [Iban]; `None` for domestic accounts, or when it cannot be verified.
+    pub fn counter_account_iban(&self) -> Option<Iban> {
+        self.b_account.parse().ok()
+    }
+
+    /// Název protiúčtu: counter-account name
+    pub fn counter_account_name(&self) -> &str {
+        &self.b_account_name
+    }
+
+    /// Kód banky: bank code of the counter-account
+    pub fn counter_bank_code(&self) -> &str {
+        &self.b_bankid
+    }
+
+    /// Název banky: bank name of the counter-account
+    pub fn counter_bank_name(&self) -> Option<&str> {
+        self.b_bank_name.as_deref()
+    }
+
+    /// KS: constant symbol
+    pub fn ks(&self) -> &str {
+        &self.ks
+    }
+
+    /// VS: variable symbol
+    pub fn vs(&self) -> &str {
+        &self.vs
+    }
+
+    /// SS: specific symbol
+    pub fn ss(&self) -> &str {
+        &self.ss
+    }
+
+    /// Uživatelská identifikace: user identification
+    pub fn user_identification(&self) -> &str {
+        &self.custom_id
+    }
+
+    /// Zpráva pro příjemce: message for recipient
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Typ: type of the transaction
+    pub fn tx_type(&self) -> &TxType {
+        &self.tx_type
+    }
+
+    /// Provedl: who executed the transaction
+    pub fn executed_by(&self) -> &str {
+        &self.who
+    }
+
+    /// Upřesnění: note, like the amount in original currency
+    pub fn note(&self) -> &str {
+        &self.note
+    }
+
+    /// Komentář: comment
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// BIC: bank identification code according to ISO 9362
+    pub fn bic(&self) -> &str {
+        &self.bic
+    }
+
+    /// BIC of the counter-account as [Bic]; `None` when missing or invalid.
+    pub fn counter_bic(&self) -> Option<Bic> {
+        self.bic().parse().ok()
+    }
+
+    /// ID pokynu: id of the instruction which caused the transaction
+    pub fn instruction_id(&self) -> Option<u64> {
+        self.id_command
+    }
 }
 
 /// 5.1 Podporované formáty dat / Typy pohybů na účtu
-#[derive(Debug, Deserialize, IntoStaticStr, EnumString)]
+#[derive(Debug, Clone, PartialEq, Deserialize, IntoStaticStr, EnumString)]
 pub enum TxType {
     #[strum(serialize = "Příjem převodem uvnitř banky")]
     FioIncome,
@@ -140,16 +320,44 @@ pub enum TxType {
     Other(String),
 }
 
+impl TxType {
+    /// Recognizes the transaction type by its Czech description; unknown descriptions are kept as [TxType::Other].
+    pub(crate) fn parse(s: String) -> Self {
+        TxType::from_str(&s).unwrap_or(TxType::Other(s))
+    }
+
+    /// The Czech description, as used by Fio.
+    pub fn as_str(&self) -> &str {
+        match self {
+            TxType::Other(s) => s,
+            known => known.into(),
+        }
+    }
+}
+
 pub(crate) mod fio_date {
     use chrono::{NaiveDate, ParseResult};
-    use serde::{Deserialize, Deserializer};
+    use serde::{Deserialize, Deserializer, Serializer};
 
     const DATEFORMAT_DD_MM_YYYY: &str = "%d.%m.%Y";
+    const DATEFORMAT_YYYY_MM_DD: &str = "%Y-%m-%d";
 
     pub fn parse_fio_date(s: &str) -> ParseResult<NaiveDate> {
         NaiveDate::parse_from_str(s, DATEFORMAT_DD_MM_YYYY)
     }
 
+    /// Formats the date the same way as it comes in CSV.
+    pub fn format_fio_date(date: &NaiveDate) -> String {
+        date.format(DATEFORMAT_DD_MM_YYYY).to_string()
+    }
+
+    /// JSON and XML formats use ISO dates with timezone offset, like `2021-06-30+0200` or `2021-06-30+02:00`.
+    /// The offset is ignored.
+    pub fn parse_iso_date(s: &str) -> ParseResult<NaiveDate> {
+        let s = s.get(0..10).unwrap_or(s);
+        NaiveDate::parse_from_str(s, DATEFORMAT_YYYY_MM_DD)
+    }
+
     pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
         where D: Deserializer<'de>,
     {
@@ -158,42 +366,60 @@ pub(crate) mod fio_date {
             .map_err(serde::de::Error::custom)
     }
 
+    pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer,
+    {
+        serializer.serialize_str(&format_fio_date(date))
+    }
+
     mod tests {
         #[test]
         fn test_parse_fio_date() -> crate::Result<()> {
             let date = super::parse_fio_date("30.06.2021")?;
             println!("date = {:?}", date);
-            assert_eq!(chrono::NaiveDate::from_ymd(2021, 6, 30), date);
+            assert_eq!(chrono::NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), date);
+            Ok(())
+        }
+
+        #[test]
+        fn test_parse_iso_date() -> crate::Result<()> {
+            let expected = chrono::NaiveDate::from_ymd_opt(2021, 6, 30).unwrap();
+            assert_eq!(expected, super::parse_iso_date("2021-06-30+0200")?);
+            assert_eq!(expected, super::parse_iso_date("2021-06-30+02:00")?);
+            assert_eq!(expected, super::parse_iso_date("2021-06-30")?);
             Ok(())
         }
     }
 }
 
 pub(crate) mod fio_decimal {
-    use std::num::ParseFloatError;
+    use serde::{Deserialize, Deserializer, Serializer};
 
-    use serde::{Deserialize, Deserializer};
+    use crate::amount::{Amount, ParseAmountError};
 
     /// Fio uses special decimal format: integer and decimal parts are separated with comma (`,`) instead of dot (`.`).
-    /// This function resolves the difference.
-    pub fn parse_fio_decimal(s: &str) -> Result<f64, ParseFloatError> {
-        let s = s.replacen(',', ".", 1); // TODO: get rid of allocation here
+    /// This function accepts both.
+    pub fn parse_fio_decimal(s: &str) -> Result<Amount, ParseAmountError> {
         s.parse()
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Amount, D::Error>
         where D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
         parse_fio_decimal(&s)
             .map_err(serde::de::Error::custom)
     }
+
+    pub fn serialize<S>(value: &Amount, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer,
+    {
+        serializer.serialize_str(&value.to_fio_string())
+    }
 }
 
 mod fio_txtype {
-    use std::str::FromStr;
-
-    use serde::{Deserialize, Deserializer};
+    use serde::{Deserialize, Deserializer, Serializer};
 
     use crate::csvdata::TxType;
 
@@ -201,7 +427,75 @@ mod fio_txtype {
         where D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        TxType::from_str(&s).or(Ok(TxType::Other(s)))
+        Ok(TxType::parse(s))
+    }
+
+    pub fn serialize<S>(tx_type: &TxType, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer,
+    {
+        serializer.serialize_str(tx_type.as_str())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::Amount;
+
+    use super::{FioTransactionsRecord, TxType};
+
+    const CSV: &str = "ID pohybu;Datum;Objem;Měna;Protiúčet;Název protiúčtu;Kód banky;Název banky;KS;VS;SS;Uživatelská identifikace;Zpráva pro příjemce;Typ;Provedl;Upřesnění;Komentář;BIC;ID pokynu
+23771345451;01.06.2021;1500,25;CZK;2702016516;Novák, Jan;2010;Fio banka, a.s.;0308;123;;Novák, Jan;nájem;Příjem převodem uvnitř banky;;;;;26010123456
+23794028126;30.06.2021;-102,93;CZK;;;;;;;;;;Nový typ;;;;;
+23794028127;30.06.2021;-500,00;CZK;DE89370400440532013000;Max Mustermann;;;;;;;;Platba v cizí měně;;;;COBADEFFXXX;
+";
+
+    fn read(csv: &str) -> anyhow::Result<Vec<FioTransactionsRecord>> {
+        let records = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_reader(csv.as_bytes())
+            .into_deserialize()
+            .collect::<Result<_, _>>()?;
+        Ok(records)
+    }
+
+    #[test]
+    fn test_accessors() -> anyhow::Result<()> {
+        let records = read(CSV)?;
+        let tx = &records[0];
+        assert_eq!(23771345451, tx.id());
+        assert_eq!(chrono::NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), tx.date());
+        assert_eq!(Amount::from_hundredths(150025), tx.amount());
+        assert_eq!("2702016516", tx.counter_account());
+        assert_eq!("2010", tx.counter_bank_code());
+        assert_eq!(Some("Fio banka, a.s."), tx.counter_bank_name());
+        assert_eq!("0308", tx.ks());
+        assert_eq!("123", tx.vs());
+        assert_eq!(&TxType::FioIncome, tx.tx_type());
+        assert_eq!(Some(26010123456), tx.instruction_id());
+        let tx = &records[1];
+        assert_eq!(None, tx.counter_bank_name());
+        assert_eq!("Nový typ", tx.tx_type().as_str());
+        assert_eq!(None, tx.instruction_id());
+        assert_eq!(None, tx.counter_account_iban());
+        let tx = &records[2];
+        assert_eq!(Some("DE"), tx.counter_account_iban().as_ref().map(|iban| iban.country()));
+        assert_eq!(Some("COBA"), tx.counter_bic().as_ref().map(|bic| bic.institution()));
+        assert_eq!(None, tx.counter_account_cz());
+        assert_eq!("2702016516/2010", records[0].counter_account_cz().unwrap().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() -> anyhow::Result<()> {
+        let records = read(CSV)?;
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+        for record in &records {
+            writer.serialize(record)?;
+        }
+        let written = String::from_utf8(writer.into_inner()?)?;
+        assert_eq!(records, read(&written)?);
+        Ok(())
+    }
+}
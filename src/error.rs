@@ -5,7 +5,9 @@ pub type Result<T, E = FioError> = core::result::Result<T, E>;
 
 #[derive(ThisError, Debug)]
 pub enum FioError {
-    #[error("Failed to prepare HTTP request - check your parameters")]
+    /// The HTTP request could not be prepared or sent, or its response could not be read;
+    /// HTTP statuses with a meaning in Fio API have their own variants
+    #[error("HTTP request failed: {0}")]
     ReqwestError(#[from] reqwest::Error),
 
     #[error("Error while parsing CSV")]
@@ -15,11 +17,32 @@ pub enum FioError {
     IoError(#[from] std::io::Error),
 
     #[error("Error parsing amount: {0}")]
-    ParseFloatError(#[from] std::num::ParseFloatError),
+    ParseAmountError(#[from] crate::amount::ParseAmountError),
+
+    #[error("Error parsing account: {0}")]
+    ParseAccountError(#[from] crate::account::ParseAccountError),
+
+    #[error("Error parsing IBAN: {0}")]
+    ParseIbanError(#[from] crate::iban::ParseIbanError),
+
+    #[error("Error parsing BIC: {0}")]
+    ParseBicError(#[from] crate::iban::ParseBicError),
+
+    #[error("Error parsing number: {0}")]
+    ParseIntError(#[from] std::num::ParseIntError),
+
+    #[error("Error while parsing JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Error while parsing XML: {0}")]
+    XmlError(#[from] roxmltree::Error),
 
     #[error("Invalid date format: {0}")]
     ParseDateError(#[from] chrono::format::ParseError),
 
+    #[error("Invalid UTF-8 text: {0}")]
+    Utf8Error(#[from] std::str::Utf8Error),
+
     /// doc/8.1: Pokoušíte se soubor odeslat jako klasický POST a nikoli jako přílohu.
     /// Viz část 6.1 Parametry pro upload dat.
     #[error("The server encoutered an internal error () that prevented it from fulfilling this request.")]
@@ -33,12 +56,18 @@ pub enum FioError {
     /// doc/8.3: Není dodržen minimální interval 30 sekund mezi stažením dat z banky / uploadem dat
     /// do banky u konkrétního tokenu (bez ohledu na typ formátu).
     /// Konkrétní token lze použít pouze 1x pro čtení nebo zápis během 30 sekund.
+    ///
+    /// Also returned when the [crate::RetryPolicy] does not allow to wait or try again.
     #[error("409 Conflict")]
     InvalidTiming,
 
     /// doc/8.4: Chyba indikuje neexistující nebo neaktivní token.
     /// Zkontrolujte si platnost a správnost tokenu v internetovém bankovnictví.
-    #[error("500 Internal Server Error")]
+    #[error("Invalid or inactive token")]
+    InvalidToken,
+
+    /// Error response with a code that is not recognized by this library.
+    #[error("500 Internal Server Error: {code} {message}")]
     InternalServerError {
         code: u16,
         message: String,
@@ -55,14 +84,82 @@ pub enum FioError {
     #[error("413 Příliš mnoho položek")]
     TooManyRows,
 
-    /// (synthetic)
+    /// errorCode 21: Výpis neexistuje
     #[error("Výpis neexistuje")]
     ReportDoesNotExist,
 
+    /// doc/6.1, errorCode 1: nalezené chyby při kontrole příkazů; dávka nebyla přijata
+    #[error("Orders failed validation: {0}")]
+    ImportValidationFailed(String),
+
+    /// doc/6.1, errorCode 2: varování kontrol - chybně vyplněné hodnoty
+    #[error("Orders have warnings: {0}")]
+    ImportWarning(String),
+
+    /// doc/6.1, errorCode 11: syntaktická chyba
+    #[error("Syntax error in imported file: {0}")]
+    ImportSyntaxError(String),
+
+    /// doc/6.1, errorCode 12: prázdný import - v souboru nejsou žádné příkazy
+    #[error("No orders in imported file")]
+    ImportNoOrders,
+
+    /// doc/6.1, errorCode 13: příliš dlouhý soubor - soubor je delší než 2 MB
+    #[error("Imported file is larger than 2 MB")]
+    ImportTooLarge,
+
+    /// doc/6.1, errorCode 14: prázdný soubor - soubor neobsahuje příkazy
+    #[error("Imported file is empty")]
+    ImportEmptyFile,
+
+    /// doc/6.1, status `fatal`: chyba na straně bankovního systému banky, všechny pokyny se odmítly
+    #[error("Bank system error {code}: {message}")]
+    BankSystemError {
+        code: u16,
+        message: String,
+    },
+
+    /// errorCode 2001: Vaše uživatelské oprávnění nepovoluje podání pokynu z tohoto účtu
+    #[error("Token does not permit orders from this account")]
+    PermissionDenied,
+
+    /// HTTP status `5xx` without a documented meaning, including `500` without the XML error body;
+    /// the server is temporarily unable to handle the request
+    #[error("Server unavailable, HTTP status {0}")]
+    ServerUnavailable(u16),
+
+    /// The call was cancelled before its completion
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// Payment order does not match doc/6.3; see [crate::Payment::validate]
+    #[error("Invalid payment order: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    InvalidPayment(Vec<crate::PaymentViolation>),
+
+    /// [crate::FioTokenPool] has no token for the operation
+    #[error("No token available for {0}")]
+    NoToken(String),
+
+    /// [crate::FioClientBuilder] got a value that cannot be used
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
     /// Missing field in the first part of CSV response
     #[error("Missing info field {0}")]
     MissingInfoField(String),
 
+    /// The format (or request) does not provide data that could be parsed into [crate::Statement]
+    #[error("Cannot parse statement from '{0}'")]
+    UnsupportedFormat(String),
+
+    /// Data in the response do not match the expected format
+    #[error("Invalid data: {0}")]
+    InvalidData(String),
+
+    /// Missing mandatory column in transaction data (JSON, XML)
+    #[error("Missing column {0}")]
+    MissingColumn(u16),
+
     #[error("Other error, see log for details")]
     OtherError {
         code: String,
@@ -73,6 +170,51 @@ pub enum FioError {
     Unknown,
 }
 
+impl FioError {
+    /// Maps the `errorCode` of Fio XML responses to the matching variant.
+    ///
+    /// Only the import codes (doc/6.1) are documented; `21` and `2001` were observed in responses.
+    /// The documentation does not list codes of failed exports, so any other code is kept
+    /// in [FioError::InternalServerError].
+    pub(crate) fn from_code(code: u16, message: String) -> Self {
+        match code {
+            1 => FioError::ImportValidationFailed(message),
+            2 => FioError::ImportWarning(message),
+            11 => FioError::ImportSyntaxError(message),
+            12 => FioError::ImportNoOrders,
+            13 => FioError::ImportTooLarge,
+            14 => FioError::ImportEmptyFile,
+            21 => FioError::ReportDoesNotExist,
+            2001 => FioError::PermissionDenied,
+            _ => FioError::InternalServerError { code, message },
+        }
+    }
+
+    /// Tells if the same request can succeed when repeated later, without any change.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FioError::InvalidTiming | FioError::BankSystemError { .. } | FioError::ServerUnavailable(_) => true,
+            FioError::ReqwestError(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+/// Translates unsuccessful HTTP response to the error.
+pub(crate) async fn error_from_response(response: reqwest::Response) -> FioError {
+    match response.status().as_u16() {
+        404 => FioError::BadRequest,
+        409 => FioError::InvalidTiming,
+        413 => FioError::TooManyRows,
+        500 => parse_xml_error(response).await,
+        status @ 501..=599 => FioError::ServerUnavailable(status),
+        _ => FioError::OtherError {
+            code: "other".to_string(),
+            message: response.status().canonical_reason().unwrap_or("?").to_string(),
+        },
+    }
+}
+
 /*
 Sample error XML:
 <response xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="https://www.fio.cz/schema/response.xsd">
@@ -84,34 +226,42 @@ Sample error XML:
 	</result>
 </response>
 */
-pub async fn parse_xml_error(response: reqwest::Response) -> FioError {
-    match response.headers().get(reqwest::header::CONTENT_TYPE) {
-        None => return FioError::OtherError { code: "missing_header".to_string(), message: "content_type".to_string() },
-        Some(content_type) => {
-            let content_type = content_type.to_str().unwrap_or("(invalid content type)");
-            if content_type != "text/xml;charset=UTF-8" {
-                return FioError::OtherError { code: "bad_content_type".to_string(), message: format!("{:?}", content_type) };
-            }
-        }
-    }
-    let response = response.text().await.unwrap();
+async fn parse_xml_error(response: reqwest::Response) -> FioError {
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+        .map(|content_type| content_type.to_str().unwrap_or("(invalid content type)").to_string());
+    let response = match response.text().await {
+        Ok(text) => text,
+        Err(e) => return FioError::ReqwestError(e),
+    };
     log::trace!("RESPONSE: {}", response);
+    match content_type {
+        Some(content_type) if content_type.starts_with("text/xml") => {}
+        content_type => return error_from_plain_body(content_type, &response),
+    }
 
     let xml: Result<FioResponse, serde_xml_rs::Error> = serde_xml_rs::from_str(&response);
     match xml {
-        Ok(xml) => {
-            // try to extract more detailed error
-            match xml.result.error_code {
-                21 => FioError::ReportDoesNotExist,
-                _ => FioError::InternalServerError { code: xml.result.error_code, message: xml.result.message }
-            }
-        }
+        Ok(xml) => FioError::from_code(xml.result.error_code, xml.result.message),
         Err(e) => {
             FioError::OtherError { code: "xml-error".to_string(), message: format!("{:?}", e) }
         }
     }
 }
 
+/// Translates HTTP 500 without the XML error body, using the few pages documented in doc/8.
+fn error_from_plain_body(content_type: Option<String>, body: &str) -> FioError {
+    if body.to_lowercase().contains("neexistující nebo neaktivní token") {
+        // doc/8.4
+        FioError::InvalidToken
+    } else if body.contains("prevented it from fulfilling this request") {
+        // doc/8.1: the generic page of the application server
+        FioError::ServerError
+    } else {
+        log::debug!("HTTP 500 with content type {:?}", content_type);
+        FioError::ServerUnavailable(500)
+    }
+}
+
 /// doc/6.1: Schéma XML odpovědi je uvedena na adrese https://www.fio.cz/schema/responseImportIB.xsd
 /// TODO: Adjust properly to completely match schema structure
 #[derive(Debug, Deserialize, PartialEq)]
@@ -141,10 +291,40 @@ struct FioResponseResult {
     /// * `warning`: varování, některý z údajů nesouhlasí (např. měna platby a měna účtu), příkazy s odpovědí warning byly přijaty bankou
     /// * `fatal`: chyba na straně bankovního systému banky, všechny pokyny se odmítly
     status: String,
-    /// suma debetních položek v dávce
-    sum_debet: Option<f64>,
-    /// suma kreditních položek v dávce
-    sum_credit: Option<f64>,
     message: String,
     detail: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{error_from_plain_body, FioError};
+
+    #[test]
+    fn test_from_code() {
+        assert!(matches!(FioError::from_code(21, String::new()), FioError::ReportDoesNotExist));
+        assert!(matches!(FioError::from_code(11, String::new()), FioError::ImportSyntaxError(_)));
+        assert!(matches!(FioError::from_code(2001, String::new()), FioError::PermissionDenied));
+        assert!(matches!(FioError::from_code(999, String::new()), FioError::InternalServerError { code: 999, .. }));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(FioError::InvalidTiming.is_retryable());
+        assert!(FioError::BankSystemError { code: 0, message: String::new() }.is_retryable());
+        assert!(FioError::ServerUnavailable(503).is_retryable());
+        assert!(!FioError::InvalidToken.is_retryable());
+        assert!(!FioError::TooManyRows.is_retryable());
+        assert!(!FioError::from_code(1, String::new()).is_retryable());
+    }
+
+    #[test]
+    fn test_plain_body() {
+        let html = Some("text/html".to_string());
+        assert!(matches!(error_from_plain_body(html.clone(), "Neexistující nebo neaktivní token"), FioError::InvalidToken));
+        assert!(matches!(error_from_plain_body(html.clone(), "<a href=\"?token=1\">"), FioError::ServerUnavailable(500)));
+        let page = "The server encountered an internal error () that prevented it from fulfilling this request.";
+        assert!(matches!(error_from_plain_body(html.clone(), page), FioError::ServerError));
+        assert!(matches!(error_from_plain_body(html, "<html></html>"), FioError::ServerUnavailable(500)));
+        assert!(error_from_plain_body(None, "").is_retryable());
+    }
+}
@@ -6,6 +6,7 @@ use chrono::NaiveDate;
 use strum_macros::IntoStaticStr;
 
 use crate::FioDatum;
+use crate::statement::StatementParser;
 
 /// 5.1 Supported transaction formats
 #[derive(IntoStaticStr)]
@@ -107,7 +108,7 @@ pub enum FioExportReq {
 }
 
 impl FioExportReq {
-    pub(crate) fn build_url(&self, token: &str) -> String {
+    pub(crate) fn build_url(&self, url_base: &str, token: &str) -> String {
         let command: &'static str = self.into();
         let params = match self {
             FioExportReq::Periods { date_start, date_end, format } =>
@@ -136,13 +137,28 @@ impl FioExportReq {
                 "statement".to_string(),
         };
         format!("{url_base}/{command}/{token}/{params}",
-                url_base = crate::client::FIOAPI_URL_BASE,
+                url_base = url_base,
                 command = command,
                 token = token,
                 params = params)
     }
 }
 
+impl FioExportReq {
+    /// The parser matching the requested format; `None` for requests which do not return any transactions.
+    pub fn statement_parser(&self) -> Option<&dyn StatementParser> {
+        match self {
+            FioExportReq::Periods { format, .. } => Some(format),
+            FioExportReq::ById { format, .. } => Some(format),
+            FioExportReq::Last { format } => Some(format),
+            FioExportReq::Merchant { format, .. } => Some(format),
+            FioExportReq::SetLastId { .. }
+            | FioExportReq::SetLastDate { .. }
+            | FioExportReq::LastStatement => None,
+        }
+    }
+}
+
 impl Display for ReportFormat {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.into())
@@ -0,0 +1,349 @@
+//! doc/5.1: GPC (ABO) format of transactions and reports, see [crate::TxFormat::Gpc] and [crate::ReportFormat::Gpc].
+//!
+//! Fixed-width text format with 128 characters per record, encoded in Windows-1250:
+//! - `074` header with account number, balances and turnovers
+//! - `075` transaction item
+//! - `076` extension with transaction identification and note
+//! - `078` extension with message for recipient (AV fields 1 and 2)
+//! - `079` extension with message for recipient (AV fields 3 and 4)
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use chrono::NaiveDate;
+
+use crate::csvdata::{fio_date, FioTransactionsRecord, TxType};
+use crate::response::*;
+use crate::{Amount, FioError, FioResponseInfo};
+
+/// Parsed GPC statement.
+#[derive(Debug)]
+pub struct GpcStatement {
+    /// account name from the `074` header
+    pub account_name: String,
+    /// statement number from the `074` header
+    pub statement_number: u16,
+    pub info: FioResponseInfo,
+    pub transactions: Vec<FioTransactionsRecord>,
+    /// header amounts
+    opening_balance: Amount,
+    closing_balance: Amount,
+    debit_turnover: Amount,
+    credit_turnover: Amount,
+}
+
+/// Fixed-width record; all positions are 1-based, as in the format specification.
+struct Record {
+    chars: Vec<char>,
+    line: usize,
+}
+
+impl Record {
+    fn text(&self, from: usize, len: usize) -> String {
+        self.chars.iter()
+            .skip(from - 1)
+            .take(len)
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    fn number(&self, from: usize, len: usize) -> crate::Result<i64> {
+        let text = self.text(from, len);
+        text.parse()
+            .map_err(|_| self.invalid(&format!("expected number at position {}, found '{}'", from, text)))
+    }
+
+    /// Like [Self::number], for fields that must fit into the unsigned type `T`.
+    fn unsigned<T: TryFrom<i64>>(&self, from: usize, len: usize) -> crate::Result<T> {
+        let number = self.number(from, len)?;
+        T::try_from(number)
+            .map_err(|_| self.invalid(&format!("number {} at position {} is out of range", number, from)))
+    }
+
+    fn date(&self, from: usize) -> crate::Result<Option<NaiveDate>> {
+        let text = self.text(from, 6);
+        if text.is_empty() || text == "000000" {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(&text, "%d%m%y")
+            .map(Some)
+            .map_err(|_| self.invalid(&format!("invalid date '{}' at position {}", text, from)))
+    }
+
+    /// Amount in hundredths, followed by a sign character (`+`, `-` or `0`).
+    fn signed_amount(&self, from: usize) -> crate::Result<Amount> {
+        let amount = Amount::from_hundredths(self.number(from, 14)?);
+        match self.chars.get(from + 13) {
+            Some('-') => Ok(-amount),
+            _ => Ok(amount),
+        }
+    }
+
+    fn invalid(&self, message: &str) -> FioError {
+        FioError::InvalidData(format!("GPC line {}: {}", self.line, message))
+    }
+}
+
+impl GpcStatement {
+    /// Decodes Windows-1250 bytes and parses the statement.
+    pub fn parse(bytes: &[u8]) -> crate::Result<Self> {
+        let (text, _) = encoding_rs::WINDOWS_1250.decode_without_bom_handling(bytes);
+        Self::parse_str(&text)
+    }
+
+    /// Parses already decoded text.
+    pub fn parse_str(text: &str) -> crate::Result<Self> {
+        let mut records = text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(n, line)| Record { chars: line.chars().collect(), line: n + 1 });
+        let header = records.next()
+            .ok_or_else(|| FioError::InvalidData("GPC: empty file".to_string()))?;
+        if header.text(1, 3) != "074" {
+            return Err(header.invalid("expected header record 074"));
+        }
+        let account = header.text(4, 16);
+        let mut info = FioResponseInfo::from(HashMap::new());
+        info.insert(INFO_ACCOUNT_ID, format_account(&account));
+        let opening_balance = header.signed_amount(46)?;
+        let closing_balance = header.signed_amount(61)?;
+        info.insert(INFO_OPENING_BALANCE, opening_balance);
+        info.insert(INFO_CLOSING_BALANCE, closing_balance);
+        if let Some(date) = header.date(40)? {
+            info.insert(INFO_DATE_START, fio_date::format_fio_date(&date));
+        }
+        let statement_date = header.date(109)?;
+        if let Some(date) = statement_date {
+            info.insert(INFO_DATE_END, fio_date::format_fio_date(&date));
+            info.insert(INFO_YEAR_LIST, date.format("%Y"));
+        }
+        let statement_number = header.unsigned::<u16>(106, 3)?;
+        info.insert(INFO_ID_LIST, statement_number);
+
+        let mut transactions: Vec<FioTransactionsRecord> = vec![];
+        for record in records {
+            match record.text(1, 3).as_str() {
+                "075" => {
+                    if record.text(4, 16) != account {
+                        return Err(record.invalid("account number does not match the header"));
+                    }
+                    transactions.push(parse_item(&record, statement_date)?);
+                }
+                "076" => {
+                    let tx = last(&mut transactions, &record)?;
+                    tx.note = record.text(36, 92);
+                }
+                "078" | "079" => {
+                    let tx = last(&mut transactions, &record)?;
+                    for text in [record.text(4, 35), record.text(39, 35)] {
+                        if text.is_empty() {
+                            continue;
+                        }
+                        if !tx.message.is_empty() {
+                            tx.message.push(' ');
+                        }
+                        tx.message.push_str(&text);
+                    }
+                }
+                other => return Err(record.invalid(&format!("unknown record type '{}'", other))),
+            }
+        }
+        if let Some(currency) = transactions.first().map(|tx| tx.currency.clone()) {
+            info.insert(INFO_CURRENCY, currency);
+        }
+        Ok(Self {
+            account_name: header.text(20, 20),
+            statement_number,
+            info,
+            transactions,
+            opening_balance,
+            closing_balance,
+            debit_turnover: header.signed_amount(76)?,
+            credit_turnover: header.signed_amount(91)?,
+        })
+    }
+
+    /// Checks that the items sum up to the turnovers declared in the header,
+    /// and that the turnovers explain the difference between opening and closing balance.
+    pub fn check_turnovers(&self) -> crate::Result<()> {
+        let (mut debit, mut credit) = (Amount::ZERO, Amount::ZERO);
+        for tx in &self.transactions {
+            if tx.value.is_negative() {
+                debit -= tx.value;
+            } else {
+                credit += tx.value;
+            }
+        }
+        if debit != self.debit_turnover.abs() || credit != self.credit_turnover.abs() {
+            return Err(FioError::InvalidData(format!(
+                "GPC: turnovers do not match the items: debit {} vs {}, credit {} vs {}",
+                self.debit_turnover.abs(), debit, self.credit_turnover.abs(), credit)));
+        }
+        if self.opening_balance - debit + credit != self.closing_balance {
+            return Err(FioError::InvalidData(format!(
+                "GPC: opening balance {} and turnovers do not match closing balance {}",
+                self.opening_balance, self.closing_balance)));
+        }
+        Ok(())
+    }
+}
+
+fn last<'a>(transactions: &'a mut [FioTransactionsRecord], record: &Record) -> crate::Result<&'a mut FioTransactionsRecord> {
+    transactions.last_mut()
+        .ok_or_else(|| record.invalid("extension record without preceding item 075"))
+}
+
+fn parse_item(record: &Record, statement_date: Option<NaiveDate>) -> crate::Result<FioTransactionsRecord> {
+    let amount = Amount::from_hundredths(record.number(49, 12)?);
+    // 1 = debit, 2 = credit, 4 = reversal of debit, 5 = reversal of credit
+    let amount = match record.text(61, 1).as_str() {
+        "1" | "5" => -amount,
+        "2" | "4" => amount,
+        code => return Err(record.invalid(&format!("unknown accounting code '{}'", code))),
+    };
+    let date = match record.date(92)? {
+        Some(date) => date,
+        None => record.date(123)?
+            .or(statement_date)
+            .ok_or_else(|| record.invalid("missing date"))?,
+    };
+    let ks = record.text(78, 4);
+    Ok(FioTransactionsRecord {
+        id_tx: record.unsigned(36, 13)?,
+        date,
+        value: amount,
+        currency: currency_code(&record.text(119, 4)),
+        b_account: format_account(&record.text(20, 16)),
+        b_account_name: record.text(98, 20),
+        b_bankid: record.text(74, 4),
+        b_bank_name: None,
+        ks: if ks.trim_start_matches('0').is_empty() { String::new() } else { ks },
+        vs: record.text(62, 10).trim_start_matches('0').to_string(),
+        ss: record.text(82, 10).trim_start_matches('0').to_string(),
+        custom_id: String::new(),
+        message: String::new(),
+        tx_type: TxType::Other(String::new()),
+        who: String::new(),
+        note: String::new(),
+        comment: String::new(),
+        bic: String::new(),
+        id_command: None,
+    })
+}
+
+/// Converts the 16-digit account (6 digits prefix, 10 digits number) to the usual `prefix-number` notation.
+fn format_account(account: &str) -> String {
+    if account.len() != 16 || !account.is_ascii() {
+        return account.to_string();
+    }
+    let prefix = account[..6].trim_start_matches('0');
+    let number = account[6..].trim_start_matches('0');
+    if prefix.is_empty() {
+        number.to_string()
+    } else {
+        format!("{}-{}", prefix, number)
+    }
+}
+
+/// GPC uses ISO 4217 numeric codes, with leading zero.
+fn currency_code(code: &str) -> String {
+    let alpha = match code.trim_start_matches('0') {
+        "203" => "CZK",
+        "978" => "EUR",
+        "840" => "USD",
+        "826" => "GBP",
+        "756" => "CHF",
+        "985" => "PLN",
+        "348" => "HUF",
+        "946" => "RON",
+        "975" => "BGN",
+        "208" => "DKK",
+        "578" => "NOK",
+        "752" => "SEK",
+        _ => code,
+    };
+    alpha.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::Amount;
+    use crate::error::Result;
+
+    use super::GpcStatement;
+
+    fn sample() -> String {
+        [
+            "0740000002345678901Novák Jan           31052100000000478951+00000000618658+00000000010293-00000000150000+006300621              ",
+            "0750000002345678901000000270201651600237713454510000001500002000000012300201003080000000000010621Novák, Jan          00203010621",
+            "076                          010621Příjem převodem uvnitř banky                                                                 ",
+            "078nájem za červen                                                       ",
+            "0750000002345678901000000000000000000237940281260000000102931000000000000000000000000000000300621Shop                00203300621",
+        ].join("\r\n")
+    }
+
+    #[test]
+    fn test_parse_gpc() -> Result<()> {
+        let statement = GpcStatement::parse_str(&sample())?;
+        assert_eq!("Novák Jan", statement.account_name);
+        assert_eq!(6, statement.statement_number);
+        let info = &statement.info;
+        assert_eq!("2345678901", info.account_id()?);
+        assert_eq!("CZK", info.currency()?);
+        assert_eq!(Amount::from_hundredths(478951), info.opening_balance()?);
+        assert_eq!(Amount::from_hundredths(618658), info.closing_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 5, 31).unwrap(), info.date_start()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), info.date_end()?);
+
+        assert_eq!(2, statement.transactions.len());
+        let tx = &statement.transactions[0];
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), tx.date);
+        assert_eq!("2702016516", tx.b_account);
+        assert_eq!("2010", tx.b_bankid);
+        assert_eq!("Novák, Jan", tx.b_account_name);
+        assert_eq!("123", tx.vs);
+        assert_eq!("0308", tx.ks);
+        assert_eq!("", tx.ss);
+        assert_eq!("Příjem převodem uvnitř banky", tx.note);
+        assert_eq!("nájem za červen", tx.message);
+        let tx = &statement.transactions[1];
+        assert_eq!(Amount::from_hundredths(-10293), tx.value);
+        assert_eq!("", tx.vs);
+        statement.check_turnovers()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_windows_1250() -> Result<()> {
+        let text = sample();
+        let (bytes, _, _) = encoding_rs::WINDOWS_1250.encode(&text);
+        let statement = GpcStatement::parse(&bytes)?;
+        assert_eq!("Novák Jan", statement.account_name);
+        assert_eq!("nájem za červen", statement.transactions[0].message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_turnover_mismatch() -> Result<()> {
+        let text = sample().replace("00000000010293-", "00000000010294-");
+        let statement = GpcStatement::parse_str(&text)?;
+        assert!(statement.check_turnovers().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_account() {
+        let text = sample().replace("0750000002345678901", "0750000002345678902");
+        assert!(GpcStatement::parse_str(&text).is_err());
+    }
+
+    #[test]
+    fn test_negative_statement_number() {
+        let text = sample().replace("+006300621", "+-06300621");
+        assert!(matches!(GpcStatement::parse_str(&text), Err(crate::FioError::InvalidData(_))));
+    }
+}
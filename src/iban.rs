@@ -0,0 +1,228 @@
+//! International account numbers (ISO 13616) and bank identifier codes (ISO 9362),
+//! as used in T2 and foreign payments (doc/6.3.2, doc/6.3.3).
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+/// Length of IBAN per country, as listed in the SWIFT IBAN registry.
+const IBAN_LENGTHS: &[(&str, usize)] = &[
+    ("AD", 24), ("AE", 23), ("AL", 28), ("AT", 20), ("AZ", 28), ("BA", 20), ("BE", 16), ("BG", 22),
+    ("BH", 22), ("BI", 27), ("BR", 29), ("BY", 28), ("CH", 21), ("CR", 22), ("CY", 28), ("CZ", 24),
+    ("DE", 22), ("DJ", 27), ("DK", 18), ("DO", 28), ("EE", 20), ("EG", 29), ("ES", 24), ("FI", 18),
+    ("FK", 18), ("FO", 18), ("FR", 27), ("GB", 22), ("GE", 22), ("GI", 23), ("GL", 18), ("GR", 27),
+    ("GT", 28), ("HR", 21), ("HU", 28), ("IE", 22), ("IL", 23), ("IQ", 23), ("IS", 26), ("IT", 27),
+    ("JO", 30), ("KW", 30), ("KZ", 20), ("LB", 28), ("LC", 32), ("LI", 21), ("LT", 20), ("LU", 20),
+    ("LV", 21), ("LY", 25), ("MC", 27), ("MD", 24), ("ME", 22), ("MK", 19), ("MN", 20), ("MR", 27),
+    ("MT", 31), ("MU", 30), ("NI", 28), ("NL", 18), ("NO", 15), ("OM", 23), ("PK", 24), ("PL", 28),
+    ("PS", 29), ("PT", 25), ("QA", 29), ("RO", 24), ("RS", 22), ("RU", 33), ("SA", 24), ("SC", 31),
+    ("SD", 18), ("SE", 24), ("SI", 19), ("SK", 24), ("SM", 27), ("SO", 23), ("ST", 25), ("SV", 28),
+    ("TL", 23), ("TN", 24), ("TR", 26), ("UA", 29), ("VA", 22), ("VG", 24), ("XK", 20), ("YE", 30),
+];
+
+#[derive(ThisError, Debug, Clone, PartialEq)]
+#[error("Invalid IBAN '{iban}': {reason}")]
+pub struct ParseIbanError {
+    iban: String,
+    reason: &'static str,
+}
+
+#[derive(ThisError, Debug, Clone, PartialEq)]
+#[error("Invalid BIC '{bic}': {reason}")]
+pub struct ParseBicError {
+    bic: String,
+    reason: &'static str,
+}
+
+/// IBAN with verified length and check digits; kept in the electronic form, without spaces.
+///
+/// ```
+/// let iban: fio_api::Iban = "DE89 3704 0044 0532 0130 00".parse()?;
+/// assert_eq!("DE", iban.country());
+/// assert_eq!("DE89370400440532013000", iban.to_string());
+/// # Ok::<(), fio_api::ParseIbanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Iban(String);
+
+impl Iban {
+    /// Two-letter country code (ISO 3166-1).
+    pub fn country(&self) -> &str {
+        &self.0[..2]
+    }
+
+    pub fn check_digits(&self) -> &str {
+        &self.0[2..4]
+    }
+
+    /// The country-specific part, following the check digits.
+    pub fn bban(&self) -> &str {
+        &self.0[4..]
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Builds IBAN from country code and BBAN, computing the check digits.
+    pub(crate) fn from_bban(country: &str, bban: &str) -> Result<Self, ParseIbanError> {
+        format!("{}{}{}", country, check_digits(country, bban), bban).parse()
+    }
+
+    /// Paper form, in groups of four characters.
+    pub fn to_print_format(&self) -> String {
+        let chars: Vec<char> = self.0.chars().collect();
+        chars.chunks(4)
+            .map(|group| group.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// ISO 13616 check digits: mod-97 of BBAN followed by country code and `00`, with letters as numbers `A` = 10 .. `Z` = 35.
+fn check_digits(country: &str, bban: &str) -> String {
+    let remainder = bban.chars()
+        .chain(country.chars())
+        .chain("00".chars())
+        .filter_map(|c| c.to_digit(36))
+        .fold(0, |remainder, value| {
+            let remainder = if value >= 10 { remainder * 100 } else { remainder * 10 };
+            (remainder + value) % 97
+        });
+    format!("{:02}", 98 - remainder)
+}
+
+impl FromStr for Iban {
+    type Err = ParseIbanError;
+
+    /// Accepts both electronic and paper form; lowercase letters are converted to uppercase.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseIbanError { iban: s.to_string(), reason };
+        let compact: String = s.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        if compact.len() < 5 || !compact.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(error("expected letters and digits only"));
+        }
+        let country = &compact[..2];
+        if !country.chars().all(|c| c.is_ascii_alphabetic()) || !compact[2..4].chars().all(|c| c.is_ascii_digit()) {
+            return Err(error("expected country code and check digits"));
+        }
+        match IBAN_LENGTHS.iter().find(|(code, _)| *code == country) {
+            None => return Err(error("unknown country")),
+            Some((_, length)) if *length != compact.len() => return Err(error("invalid length for the country")),
+            Some(_) => {}
+        }
+        if check_digits(country, &compact[4..]) != compact[2..4] {
+            return Err(error("invalid check digits"));
+        }
+        Ok(Self(compact))
+    }
+}
+
+impl Display for Iban {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Bank identifier code, with the structure verified by ISO 9362:
+/// 4 letters of institution, 2 letters of country, 2 characters of location and optional 3 characters of branch.
+///
+/// ```
+/// let bic: fio_api::Bic = "FIOBCZPP".parse()?;
+/// assert_eq!("CZ", bic.country());
+/// assert_eq!("FIOBCZPPXXX", bic.to_bic11());
+/// # Ok::<(), fio_api::ParseBicError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bic(String);
+
+impl Bic {
+    pub fn institution(&self) -> &str {
+        &self.0[..4]
+    }
+
+    pub fn country(&self) -> &str {
+        &self.0[4..6]
+    }
+
+    pub fn location(&self) -> &str {
+        &self.0[6..8]
+    }
+
+    /// Branch code; `None` for 8-character BIC.
+    pub fn branch(&self) -> Option<&str> {
+        self.0.get(8..)
+            .filter(|branch| !branch.is_empty())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 11-character form, with `XXX` standing for the primary office.
+    pub fn to_bic11(&self) -> String {
+        format!("{}{}", &self.0[..8], self.branch().unwrap_or("XXX"))
+    }
+}
+
+impl FromStr for Bic {
+    type Err = ParseBicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseBicError { bic: s.to_string(), reason };
+        let bic = s.trim().to_ascii_uppercase();
+        if !bic.is_ascii() {
+            return Err(error("expected letters and digits only"));
+        }
+        if bic.len() != 8 && bic.len() != 11 {
+            return Err(error("expected 8 or 11 characters"));
+        }
+        if !bic[..6].chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(error("expected letters of institution and country"));
+        }
+        if !bic[6..].chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(error("expected letters or digits of location and branch"));
+        }
+        Ok(Self(bic))
+    }
+}
+
+impl Display for Bic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bic, Iban};
+
+    #[test]
+    fn test_iban() {
+        for valid in ["CZ65 0800 0000 1920 0014 5399", "GB82WEST12345698765432", "sk3112000000198742637541", "NO9386011117947"] {
+            assert!(valid.parse::<Iban>().is_ok(), "{}", valid);
+        }
+        assert!("CZ6608000000192000145399".parse::<Iban>().is_err(), "check digits");
+        assert!("CZ650800000019200014539".parse::<Iban>().is_err(), "length");
+        assert!("XX650800000019200014539".parse::<Iban>().is_err(), "country");
+        let iban: Iban = "GB82WEST12345698765432".parse().unwrap();
+        assert_eq!("GB82 WEST 1234 5698 7654 32", iban.to_print_format());
+        assert_eq!("WEST12345698765432", iban.bban());
+        assert_eq!(iban, Iban::from_bban("GB", "WEST12345698765432").unwrap());
+    }
+
+    #[test]
+    fn test_bic() {
+        let bic: Bic = "giBaczpxxxx".parse().unwrap();
+        assert_eq!("GIBA", bic.institution());
+        assert_eq!(Some("XXX"), bic.branch());
+        assert_eq!(None, "FIOBCZPP".parse::<Bic>().unwrap().branch());
+        assert!("FIOBCZP".parse::<Bic>().is_err());
+        assert!("FIO1CZPP".parse::<Bic>().is_err());
+        assert!("FIOBCZP_XXX".parse::<Bic>().is_err());
+        assert!("ABCDEČP".parse::<Bic>().is_err());
+    }
+}
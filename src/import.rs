@@ -4,10 +4,11 @@ use std::collections::HashMap;
 use std::io::ErrorKind;
 
 use chrono::NaiveDate;
+use roxmltree::{Document, Node};
 use serde::Deserialize;
-use strum_macros::IntoStaticStr;
+use strum_macros::{EnumString, IntoStaticStr};
 
-use crate::Result;
+use crate::{Amount, Bic, CzAccount, FioError, Iban, Result};
 use crate::tiny_xml::TinyXml;
 
 // TODO: enhance error xml to receive all fields
@@ -37,6 +38,15 @@ impl ToPaymentXml for DomesticPayment {
     }
 }
 
+/// Support for single payment of any type
+impl ToPaymentXml for Payment {
+    fn to_payment_xml(&self) -> Result<String> {
+        let mut doc = new_orders_doc()?;
+        self.add_to(&mut doc)?;
+        Ok(doc.into_xml()?)
+    }
+}
+
 /// Support for array of domestic payments
 impl ToPaymentXml for &[Payment] {
     fn to_payment_xml(&self) -> Result<String> {
@@ -49,7 +59,10 @@ impl ToPaymentXml for &[Payment] {
 }
 
 /// Name of the special field holding XML type of transaction.
-const TRANSACTION_TYPE: &'static str = ".xml_transaction_type";
+const TRANSACTION_TYPE: &str = ".xml_transaction_type";
+
+/// XML element names of the supported payment orders.
+const TRANSACTION_TYPES: &[&str] = &["DomesticTransaction", "T2Transaction", "ForeignTransaction"];
 
 /// Valid fields and their required order within XML document.
 const PAYMENT_FIELDS: &[&str] = &["accountFrom", "currency", "amount", "accountTo",
@@ -58,8 +71,108 @@ const PAYMENT_FIELDS: &[&str] = &["accountFrom", "currency", "amount", "accountT
     "remittanceInfo1", "remittanceInfo2", "remittanceInfo3", "remittanceInfo4",
     "detailsOfCharges", "paymentReason", "paymentType"];
 
+/// Format of a field, in the notation of doc/6.3.
+#[derive(Clone, Copy)]
+enum FieldFormat {
+    /// `Nn`: up to N digits
+    Digits(usize),
+    /// `N!n`: exactly N digits
+    ExactDigits(usize),
+    /// `Ni`: up to N characters
+    Text(usize),
+    /// `3!x`: ISO 4217 currency code
+    Currency,
+    /// `11!x`: BIC, 8 or 11 characters
+    Bic,
+    /// `34x`: IBAN
+    Iban,
+    /// `18d`: positive amount
+    Amount,
+    /// `RRRR-MM-DD`
+    Date,
+}
+
+fn field_format(key: &str) -> Option<FieldFormat> {
+    let format = match key {
+        "accountFrom" => FieldFormat::Digits(16),
+        "currency" => FieldFormat::Currency,
+        "amount" => FieldFormat::Amount,
+        "bankCode" => FieldFormat::ExactDigits(4),
+        "ks" => FieldFormat::Digits(4),
+        "vs" | "ss" => FieldFormat::Digits(10),
+        "bic" => FieldFormat::Bic,
+        "date" => FieldFormat::Date,
+        "messageForRecipient" => FieldFormat::Text(140),
+        "comment" => FieldFormat::Text(255),
+        "benefName" | "benefStreet" | "benefCity" => FieldFormat::Text(35),
+        "benefCountry" => FieldFormat::Text(3),
+        "remittanceInfo1" | "remittanceInfo2" | "remittanceInfo3" | "remittanceInfo4" => FieldFormat::Text(35),
+        "detailsOfCharges" | "paymentType" => FieldFormat::ExactDigits(6),
+        "paymentReason" => FieldFormat::ExactDigits(3),
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// Fields required by the given XML transaction type.
+fn mandatory_fields(transaction_type: &str) -> &'static [&'static str] {
+    match transaction_type {
+        "DomesticTransaction" => &["accountFrom", "currency", "amount", "accountTo", "bankCode", "date"],
+        "T2Transaction" => &["accountFrom", "currency", "amount", "accountTo", "bic", "date", "benefName"],
+        "ForeignTransaction" => &["accountFrom", "currency", "amount", "accountTo", "bic", "date",
+            "benefName", "benefStreet", "benefCity", "benefCountry", "remittanceInfo1", "detailsOfCharges"],
+        _ => &["accountFrom", "currency", "amount", "accountTo", "date"],
+    }
+}
+
+/// `18d`: digits with optional `.` and at most two decimal places, greater than zero.
+fn is_amount(value: &str) -> bool {
+    let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let valid = match value.split_once('.') {
+        Some((units, decimals)) => is_digits(units) && is_digits(decimals) && decimals.len() <= 2,
+        None => is_digits(value),
+    };
+    valid && value.chars().filter(char::is_ascii_digit).count() <= 18
+        && value.chars().any(|c| matches!(c, '1'..='9'))
+}
+
+fn check_format(format: FieldFormat, value: &str) -> std::result::Result<(), String> {
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    match format {
+        FieldFormat::Digits(max) if !is_digits(value) || value.len() > max =>
+            Err(format!("expected up to {} digits", max)),
+        FieldFormat::ExactDigits(len) if !is_digits(value) || value.len() != len =>
+            Err(format!("expected exactly {} digits", len)),
+        FieldFormat::Text(max) if value.chars().count() > max =>
+            Err(format!("longer than {} characters", max)),
+        FieldFormat::Currency if value.len() != 3 || !value.chars().all(|c| c.is_ascii_uppercase()) =>
+            Err("expected ISO 4217 currency code".to_string()),
+        FieldFormat::Bic => value.parse::<Bic>().map(|_| ()).map_err(|e| e.to_string()),
+        FieldFormat::Iban => value.parse::<Iban>().map(|_| ()).map_err(|e| e.to_string()),
+        FieldFormat::Amount if !is_amount(value) =>
+            Err("expected positive amount with `.` and up to 2 decimal places".to_string()),
+        FieldFormat::Date if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_err() =>
+            Err("expected date RRRR-MM-DD".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Field of a payment order violating the specification in doc/6.3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentViolation {
+    /// name of the XML element, like `accountTo`
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for PaymentViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
 /// Generalized payment
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct Payment {
     properties: HashMap<&'static str, String>,
 }
@@ -69,6 +182,92 @@ impl Payment {
         self.properties.insert(key, value.to_string());
     }
 
+    /// XML element name of the order, like `DomesticTransaction`.
+    pub fn transaction_type(&self) -> Option<&str> {
+        self.properties.get(TRANSACTION_TYPE).map(String::as_str)
+    }
+
+    /// Value of the field, by the name of its XML element, like `accountTo`.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.properties.get(field).map(String::as_str)
+    }
+
+    /// Reads payment orders from `Import/Orders` XML, as produced by `ToPaymentXml`.
+    ///
+    /// Field values are kept verbatim, including leading and trailing whitespace; use [Payment::validate] to check them.
+    /// Fails with [FioError::InvalidData] on unknown elements and on a field repeated within an order.
+    pub fn parse_batch(xml: &str) -> Result<Vec<Payment>> {
+        let doc = Document::parse(xml)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("Import") {
+            return Err(FioError::InvalidData(format!("Unexpected root element '{}'", root.tag_name().name())));
+        }
+        let orders = crate::xmldata::child(root, "Orders")
+            .ok_or_else(|| FioError::InvalidData("Missing element 'Orders'".to_string()))?;
+        orders.children()
+            .filter(Node::is_element)
+            .map(Self::parse_order)
+            .collect()
+    }
+
+    fn parse_order(node: Node) -> Result<Payment> {
+        let transaction_type = TRANSACTION_TYPES.iter()
+            .find(|&&name| node.has_tag_name(name))
+            .ok_or_else(|| FioError::InvalidData(format!("Unknown payment order '{}'", node.tag_name().name())))?;
+        let mut payment = Payment::default();
+        payment.set(TRANSACTION_TYPE, transaction_type);
+        for field_node in node.children().filter(Node::is_element) {
+            let field = PAYMENT_FIELDS.iter()
+                .find(|&&name| field_node.has_tag_name(name))
+                .ok_or_else(|| FioError::InvalidData(format!("Unknown field '{}' in {}", field_node.tag_name().name(), transaction_type)))?;
+            if payment.properties.contains_key(field) {
+                return Err(FioError::InvalidData(format!("Duplicate field '{}' in {}", field, transaction_type)));
+            }
+            payment.set(field, field_node.text().unwrap_or_default());
+        }
+        Ok(payment)
+    }
+
+    /// Checks the fields against doc/6.3, so that the bank does not reject the whole batch because of this order.
+    ///
+    /// Fails with [FioError::InvalidPayment] listing all violations found.
+    pub fn validate(&self) -> Result<()> {
+        let transaction_type = self.properties.get(TRANSACTION_TYPE).map(String::as_str).unwrap_or_default();
+        let mut violations = vec![];
+        for &field in mandatory_fields(transaction_type) {
+            if self.properties.get(field).map_or(true, |value| value.is_empty()) {
+                violations.push(PaymentViolation { field, message: "missing mandatory field".to_string() });
+            }
+        }
+        for &field in PAYMENT_FIELDS {
+            let value = match self.properties.get(field) {
+                Some(value) if !value.is_empty() => value,
+                _ => continue,
+            };
+            let format = match (field, transaction_type) {
+                // checked with the bank code below
+                ("accountTo", "DomesticTransaction") => None,
+                ("accountTo", _) => Some(FieldFormat::Iban),
+                _ => field_format(field),
+            };
+            if let Some(Err(message)) = format.map(|format| check_format(format, value)) {
+                violations.push(PaymentViolation { field, message });
+            }
+        }
+        if transaction_type == "DomesticTransaction" && !violations.iter().any(|v| v.field == "bankCode") {
+            if let (Some(account), Some(bank_code)) = (self.properties.get("accountTo"), self.properties.get("bankCode")) {
+                if let Err(e) = format!("{}/{}", account, bank_code).parse::<CzAccount>() {
+                    violations.push(PaymentViolation { field: "accountTo", message: e.to_string() });
+                }
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(FioError::InvalidPayment(violations))
+        }
+    }
+
     fn add_to(&self, doc: &mut TinyXml) -> std::io::Result<()> {
         let transaction_xml_type = self.properties.get(TRANSACTION_TYPE)
             .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "Missing transaction type property"))?;
@@ -104,9 +303,9 @@ pub trait PaymentBuilder: Sized {
         self.set("currency", value)
     }
 
-    /// (mandatory, 18d) částka příkazu
-    fn amount(self, value: f64) -> Self {
-        self.set("amount", value.to_string())
+    /// (mandatory, 18d) částka příkazu; always written with two decimal places
+    fn amount(self, value: Amount) -> Self {
+        self.set("amount", value)
     }
 
     /// * `date` : (mandatory, RRRR-MM-DD) datum
@@ -116,7 +315,7 @@ pub trait PaymentBuilder: Sized {
 
     /// helper to set current date
     fn date_today(self) -> Self {
-        self.date(chrono::Local::now().date().naive_local())
+        self.date(chrono::Local::now().date_naive())
     }
 
     /// (optional, 255i) Vaše označení
@@ -130,7 +329,7 @@ pub trait PaymentBuilder: Sized {
     }
 }
 
-#[derive(Debug, Deserialize, IntoStaticStr)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, IntoStaticStr, EnumString)]
 pub enum PaymentType {
     #[strum(serialize = "431001")]
     Standard,
@@ -166,6 +365,11 @@ pub trait DomesticTransaction: PaymentBuilder + DomesticSymbolsBuilder {
             .set("bankCode", bank_code.to_string())
     }
 
+    /// Like [Self::account_to], with account number already verified.
+    fn cz_account_to(self, account: &CzAccount) -> Self {
+        self.account_to(&account.account_number(), account.bank_code())
+    }
+
     /// (optional, 140i) zpráva pro příjemce
     fn message_for_recipient<S: ToString>(self, value: S) -> Self {
         self.set("messageForRecipient", value)
@@ -194,6 +398,11 @@ impl DomesticPayment {
             .currency(currency)
             .date_today()
     }
+
+    /// See [Payment::validate].
+    pub fn validate(&self) -> Result<()> {
+        self.payment.validate()
+    }
 }
 
 impl PaymentBuilder for DomesticPayment {
@@ -215,25 +424,25 @@ impl From<DomesticPayment> for Payment {
 
 pub trait AbroadTransaction: PaymentBuilder {
     /// mezinárodní číslo bankovního účtu příjemce/inkasovaného dle standardu ISO 13616
-    fn account_to<S: ToString>(self, value: S) -> Self {
-        self.set("accountTo", value)
+    fn account_to(self, iban: &Iban) -> Self {
+        self.set("accountTo", iban)
     }
 
     /// bankovní identifikační kód dle standardu ISO 9362
-    fn bic<S: ToString>(self, value: S) -> Self {
-        self.set("bic", value)
+    fn bic(self, bic: &Bic) -> Self {
+        self.set("bic", bic)
     }
     fn benef_name<S: ToString>(self, value: S) -> Self {
         self.set("benefName", value)
     }
     fn benef_street<S: ToString>(self, value: S) -> Self {
-        self.set("benefName", value)
+        self.set("benefStreet", value)
     }
     fn benef_city<S: ToString>(self, value: S) -> Self {
-        self.set("benefName", value)
+        self.set("benefCity", value)
     }
     fn benef_country<S: ToString>(self, value: S) -> Self {
-        self.set("benefName", value)
+        self.set("benefCountry", value)
     }
     fn remittance_info_1<S: ToString>(self, value: S) -> Self {
         self.set("remittanceInfo1", value)
@@ -268,6 +477,11 @@ impl T2Payment {
             .currency(currency.to_string())
             .date_today()
     }
+
+    /// See [Payment::validate].
+    pub fn validate(&self) -> Result<()> {
+        self.payment.validate()
+    }
 }
 
 impl PaymentBuilder for T2Payment {
@@ -290,7 +504,7 @@ impl From<T2Payment> for Payment {
 }
 
 /// poplatky
-#[derive(Debug, Deserialize, IntoStaticStr)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, IntoStaticStr, EnumString)]
 pub enum DetailsOfCharges {
     /// 470501 – vše plátce (OUR)
     #[strum(serialize = "470501")]
@@ -326,6 +540,11 @@ impl ForeignPayment {
             .currency(currency.to_string())
             .date_today()
     }
+
+    /// See [Payment::validate].
+    pub fn validate(&self) -> Result<()> {
+        self.payment.validate()
+    }
 }
 
 impl PaymentBuilder for ForeignPayment {
@@ -353,7 +572,7 @@ mod tests {
     fn xyz() {
         let czpayment = Payment::from(DomesticPayment::new("a", "CZK")
             .date_today()
-            .amount(3.4)
+            .amount(Amount::from_hundredths(340))
             .account_to("1234567890", "1234")
             .ks("a")
             .vs("adsf"));
@@ -361,18 +580,118 @@ mod tests {
 
         let t2: Payment = T2Payment::new("dsaf", "EUR")
             .date_today()
-            .amount(34.1)
-            .account_to("fsdafs")
-            .bic("dfas")
+            .amount(Amount::from_hundredths(3410))
+            .account_to(&"DE89370400440532013000".parse().unwrap())
+            .bic(&"COBADEFF".parse().unwrap())
             .into();
         println!("t2: {:?}", t2.properties);
 
         let fp = ForeignPayment::new("a", "USD")
             .date_today()
-            .amount(43.1)
-            .account_to("fadfa")
-            .bic("fasdfa")
+            .amount(Amount::from_hundredths(4310))
+            .account_to(&"GB82WEST12345698765432".parse().unwrap())
+            .bic(&"NWBKGB2L".parse().unwrap())
             .details_of_charges(DetailsOfCharges::SHA);
         println!("fp: {:?}", fp.payment.properties);
     }
+
+    #[test]
+    fn test_amount_is_exact() -> Result<()> {
+        let payment = DomesticPayment::new("2345678901", "CZK")
+            .amount("102.93".parse()?)
+            .account_to("1234567890", "0800");
+        let xml = payment.to_payment_xml()?;
+        assert!(xml.contains("<amount>102.93</amount>"), "{}", xml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_ok() -> Result<()> {
+        DomesticPayment::new("2345678901", "CZK")
+            .amount(Amount::from_hundredths(10293))
+            .cz_account_to(&"19-2000145399/0800".parse()?)
+            .vs("2021006")
+            .validate()?;
+        ForeignPayment::new("2345678901", "USD")
+            .amount(Amount::from_hundredths(4310))
+            .account_to(&"DE89370400440532013000".parse()?)
+            .bic(&"COBADEFFXXX".parse()?)
+            .benef_name("Max Mustermann")
+            .benef_street("Hauptstrasse 1")
+            .benef_city("Berlin")
+            .benef_country("DE")
+            .remittance_info_1("invoice 1")
+            .details_of_charges(DetailsOfCharges::SHA)
+            .validate()
+    }
+
+    #[test]
+    fn test_validate_reports_all_violations() {
+        let result = DomesticPayment::new("2345678901", "czk")
+            .amount(Amount::from_hundredths(-100))
+            .account_to("1234567890", "80")
+            .ks("12345")
+            .vs("abc")
+            .message_for_recipient("x".repeat(141))
+            .validate();
+        let violations = match result {
+            Err(FioError::InvalidPayment(violations)) => violations,
+            other => panic!("unexpected {:?}", other),
+        };
+        let fields: Vec<_> = violations.iter().map(|v| v.field).collect();
+        assert_eq!(vec!["currency", "amount", "bankCode", "ks", "vs", "messageForRecipient"], fields);
+
+        let result = DomesticPayment::new("2345678901", "CZK")
+            .amount(Amount::from_hundredths(100))
+            .account_to("1234567890", "0800")
+            .validate();
+        assert!(matches!(result, Err(FioError::InvalidPayment(v)) if v[0].field == "accountTo"), "checksum");
+
+        let result = T2Payment::new("2345678901", "EUR")
+            .amount(Amount::from_hundredths(100))
+            .validate();
+        let fields: Vec<_> = match result {
+            Err(FioError::InvalidPayment(violations)) => violations.iter().map(|v| v.field).collect(),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(vec!["accountTo", "bic", "benefName"], fields);
+    }
+
+    #[test]
+    fn test_check_amount_format() {
+        for value in ["102.93", "1500", "0.5", "1.00"] {
+            assert_eq!(Ok(()), check_format(FieldFormat::Amount, value), "{}", value);
+        }
+        for value in ["102,93", "102.930", "0.00", "-1.00", ".5", "1.", "1 000", ""] {
+            assert!(check_format(FieldFormat::Amount, value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_batch() -> Result<()> {
+        let payments = Payment::parse_batch(include_str!("../examples/payment.xml"))?;
+        assert_eq!(1, payments.len());
+        assert_eq!(Some("DomesticTransaction"), payments[0].transaction_type());
+        assert_eq!(Some("102.93"), payments[0].get("amount"));
+        assert_eq!(Some("test comment"), payments[0].get("comment"));
+
+        let batch = vec![
+            payments[0].clone(),
+            ForeignPayment::new("2345678901", "USD")
+                .amount(Amount::from_hundredths(4310))
+                .account_to(&"DE89370400440532013000".parse()?)
+                .bic(&"COBADEFFXXX".parse()?)
+                .benef_name(" Max & Moritz ")
+                .remittance_info_1("invoice <1>")
+                .into(),
+        ];
+        let xml = batch.as_slice().to_payment_xml()?;
+        assert_eq!(batch, Payment::parse_batch(&xml)?);
+
+        let unknown = xml.replace("ForeignTransaction", "CardTransaction");
+        assert!(matches!(Payment::parse_batch(&unknown), Err(FioError::InvalidData(_))));
+        let duplicate = xml.replace("<amount>", "<amount>1.00</amount><amount>");
+        assert!(matches!(Payment::parse_batch(&duplicate), Err(FioError::InvalidData(_))));
+        Ok(())
+    }
 }
@@ -0,0 +1,259 @@
+//! doc/6.1: Response to the import of payment orders.
+//!
+//! Schema: https://www.fio.cz/schema/responseImport.xsd
+//!
+//! Sample:
+//! ```xml
+//! <responseImport>
+//!   <result>
+//!     <errorCode>0</errorCode>
+//!     <idInstruction>1801400777</idInstruction>
+//!     <status>ok</status>
+//!     <sums>
+//!       <sum id="CZK">
+//!         <sumCredit>0</sumCredit>
+//!         <sumDebet>444.90</sumDebet>
+//!       </sum>
+//!     </sums>
+//!   </result>
+//!   <ordersDetails>
+//!     <detail id="1"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
+//!   </ordersDetails>
+//! </responseImport>
+//! ```
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use roxmltree::{Document, Node};
+use strum_macros::{EnumString, IntoStaticStr};
+
+use crate::xmldata::child;
+use crate::{Amount, FioError};
+
+/// Status of the whole batch, or of a single order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoStaticStr, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ImportStatus {
+    /// příkaz přijat
+    Ok,
+    /// varování, některý z údajů nesouhlasí (např. měna platby a měna účtu);
+    /// příkazy s odpovědí warning byly přijaty bankou
+    Warning,
+    /// hrubá chyba v příkazu, dávka se všemi příkazy nebude přijata
+    Error,
+    /// chyba na straně bankovního systému banky, všechny pokyny se odmítly
+    Fatal,
+}
+
+impl ImportStatus {
+    /// `true` for statuses meaning that the orders were accepted by the bank.
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, ImportStatus::Ok | ImportStatus::Warning)
+    }
+}
+
+/// Sums of the batch items in one currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportSum {
+    /// suma kreditních položek v dávce
+    pub credit: Amount,
+    /// suma debetních položek v dávce
+    pub debet: Amount,
+}
+
+/// Message related to single order of the batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportMessage {
+    pub status: ImportStatus,
+    pub error_code: u16,
+    pub text: String,
+}
+
+/// Parsed response to [crate::FioClientWithImport::import].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportResult {
+    /// * `0`:  ok - příkaz byl přijat
+    /// * `1`:  nalezené chyby při kontrole příkazů
+    /// * `2`:  varování kontrol - chybně vyplněné hodnoty
+    /// * `11`: syntaktická chyba
+    /// * `12`: prázdný import - v souboru nejsou žádné příkazy
+    /// * `13`: příliš dlouhý soubor - soubor je delší než 2 MB
+    /// * `14`: prázdný soubor - soubor neobsahuje příkazy
+    /// * `2001`: uživatelské oprávnění nepovoluje podání pokynu z tohoto účtu
+    pub error_code: u16,
+    /// číslo dávky - jednoznačný identifikátor dávky
+    pub id_instruction: Option<String>,
+    pub status: ImportStatus,
+    /// Textual message of the whole batch, if provided
+    pub message: Option<String>,
+    /// Sums keyed by currency
+    pub sums: BTreeMap<String, ImportSum>,
+    /// Messages keyed by order index; the first order in the batch has index `1`.
+    pub orders_details: BTreeMap<u32, Vec<ImportMessage>>,
+}
+
+impl ImportResult {
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let doc = Document::parse(text)?;
+        let root = doc.root_element();
+        let result = child(root, "result")
+            .ok_or_else(|| FioError::InvalidData("Missing element 'result'".to_string()))?;
+        let error_code = match child_text(result, "errorCode") {
+            None => return Err(FioError::InvalidData("Missing element 'errorCode'".to_string())),
+            Some(code) => code.parse()?,
+        };
+        let status = match child_text(result, "status") {
+            None => return Err(FioError::InvalidData("Missing element 'status'".to_string())),
+            Some(status) => parse_status(status)?,
+        };
+        let mut sums = BTreeMap::new();
+        if let Some(node) = child(result, "sums") {
+            for sum in node.children().filter(|n| n.has_tag_name("sum")) {
+                let currency = sum.attribute("id").unwrap_or_default().to_string();
+                let credit = child_amount(sum, "sumCredit")?;
+                let debet = child_amount(sum, "sumDebet")?;
+                sums.insert(currency, ImportSum { credit, debet });
+            }
+        }
+        let mut orders_details = BTreeMap::new();
+        if let Some(node) = child(root, "ordersDetails") {
+            for detail in node.children().filter(|n| n.has_tag_name("detail")) {
+                let index = detail.attribute("id")
+                    .ok_or_else(|| FioError::InvalidData("Missing order detail id".to_string()))?
+                    .parse()?;
+                let messages = match child(detail, "messages") {
+                    None => vec![],
+                    Some(messages) => messages.children()
+                        .filter(|n| n.has_tag_name("message"))
+                        .map(parse_message)
+                        .collect::<crate::Result<_>>()?,
+                };
+                orders_details.insert(index, messages);
+            }
+        }
+        Ok(Self {
+            error_code,
+            id_instruction: child_text(result, "idInstruction").map(str::to_string),
+            status,
+            message: child_text(result, "message").map(str::to_string),
+            sums,
+            orders_details,
+        })
+    }
+
+    /// `true` if the batch was accepted, possibly with warnings.
+    pub fn is_accepted(&self) -> bool {
+        self.status.is_accepted()
+    }
+
+    /// The error matching `errorCode`, if the batch was not accepted.
+    pub fn error(&self) -> Option<FioError> {
+        let message = self.message.clone().unwrap_or_default();
+        match self.status {
+            ImportStatus::Ok | ImportStatus::Warning => None,
+            ImportStatus::Fatal => Some(FioError::BankSystemError { code: self.error_code, message }),
+            ImportStatus::Error => Some(FioError::from_code(self.error_code, message)),
+        }
+    }
+
+    /// Indexes and messages of the orders that were not accepted.
+    pub fn failed_orders(&self) -> impl Iterator<Item=(u32, &Vec<ImportMessage>)> {
+        self.orders_details.iter()
+            .filter(|(_, messages)| messages.iter().any(|m| !m.status.is_accepted()))
+            .map(|(&index, messages)| (index, messages))
+    }
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn child_amount(node: Node, name: &str) -> crate::Result<Amount> {
+    match child_text(node, name) {
+        None => Ok(Amount::ZERO),
+        Some(text) => Ok(text.parse()?),
+    }
+}
+
+fn parse_status(text: &str) -> crate::Result<ImportStatus> {
+    ImportStatus::from_str(text)
+        .map_err(|_| FioError::InvalidData(format!("Unknown import status '{}'", text)))
+}
+
+fn parse_message(node: Node) -> crate::Result<ImportMessage> {
+    Ok(ImportMessage {
+        status: parse_status(node.attribute("status").unwrap_or_default())?,
+        error_code: node.attribute("errorCode").unwrap_or("0").parse()?,
+        text: node.text().unwrap_or_default().trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Result;
+    use crate::{Amount, FioError};
+
+    use super::{ImportResult, ImportStatus};
+
+    #[test]
+    fn test_parse_ok() -> Result<()> {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<responseImport xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://www.fio.cz/schema/responseImport.xsd">
+<result>
+  <errorCode>0</errorCode>
+  <idInstruction>1801400777</idInstruction>
+  <status>ok</status>
+  <sums>
+   <sum id="CZK">
+    <sumCredit>0</sumCredit>
+    <sumDebet>444.90</sumDebet>
+   </sum>
+  </sums>
+</result>
+<ordersDetails>
+ <detail id="1"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
+ <detail id="2"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
+</ordersDetails>
+</responseImport>"#;
+        let result = ImportResult::parse(xml)?;
+        assert_eq!(0, result.error_code);
+        assert_eq!(Some("1801400777"), result.id_instruction.as_deref());
+        assert_eq!(ImportStatus::Ok, result.status);
+        assert_eq!(Amount::from_hundredths(44490), result.sums["CZK"].debet);
+        assert_eq!(Amount::ZERO, result.sums["CZK"].credit);
+        assert_eq!(2, result.orders_details.len());
+        assert!(result.is_accepted());
+        assert_eq!(0, result.failed_orders().count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_failed_order() -> Result<()> {
+        let xml = r#"<responseImport>
+<result>
+  <errorCode>1</errorCode>
+  <status>error</status>
+</result>
+<ordersDetails>
+ <detail id="1"><messages><message status="ok" errorCode="0">OK</message></messages></detail>
+ <detail id="2"><messages>
+  <message status="error" errorCode="1032">Pole číslo účtu nesmí být prázdné.</message>
+  <message status="warning" errorCode="1082">Datum splatnosti je v minulosti.</message>
+ </messages></detail>
+</ordersDetails>
+</responseImport>"#;
+        let result = ImportResult::parse(xml)?;
+        assert_eq!(ImportStatus::Error, result.status);
+        assert!(!result.is_accepted());
+        let failed: Vec<_> = result.failed_orders().collect();
+        assert_eq!(1, failed.len());
+        assert_eq!(2, failed[0].0);
+        assert_eq!(1032, failed[0].1[0].error_code);
+        assert_eq!(ImportStatus::Warning, failed[0].1[1].status);
+        assert!(matches!(result.error(), Some(FioError::ImportValidationFailed(_))));
+        Ok(())
+    }
+}
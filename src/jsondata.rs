@@ -0,0 +1,267 @@
+//! doc/5.1: JSON format of transactions and reports.
+//!
+//! Unlike CSV, the JSON format identifies each column by a stable id (`column22` is always `ID pohybu`),
+//! so it is not affected by changes of the Czech headings.
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::csvdata::{fio_date, FioTransactionsRecord};
+use crate::response::*;
+use crate::{Amount, FioResponseInfo};
+
+/// Parsed JSON response, as received for [crate::TxFormat::Json] or [crate::ReportFormat::Json].
+#[derive(Debug)]
+pub struct JsonStatement {
+    pub info: JsonStatementInfo,
+    pub transactions: Vec<FioTransactionsRecord>,
+}
+
+/// The `accountStatement.info` part of JSON response.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonStatementInfo {
+    /// číslo účtu
+    pub account_id: String,
+    /// kód banky
+    pub bank_id: String,
+    /// měna účtu
+    pub currency: String,
+    /// mezinárodní číslo účtu dle ISO 13616
+    pub iban: String,
+    /// identifikační kód banky dle ISO 9362
+    pub bic: String,
+    /// počáteční zůstatek na účtu na počátku zvoleného období
+    #[serde(with = "json_amount")]
+    pub opening_balance: Amount,
+    /// konečný zůstatek na účtu na konci zvoleného období
+    #[serde(with = "json_amount")]
+    pub closing_balance: Amount,
+    /// počátek zvoleného období
+    #[serde(with = "json_date")]
+    pub date_start: NaiveDate,
+    /// konec zvoleného období
+    #[serde(with = "json_date")]
+    pub date_end: NaiveDate,
+    /// rok výpisu (only for reports)
+    pub year_list: Option<u16>,
+    /// číslo výpisu (only for reports)
+    pub id_list: Option<u32>,
+    /// číslo prvního pohybu v daném výběru
+    pub id_from: Option<u64>,
+    /// číslo posledního pohybu v daném výběru
+    pub id_to: Option<u64>,
+    /// číslo posledního staženého pohybu
+    pub id_last_download: Option<u64>,
+}
+
+impl From<&JsonStatementInfo> for FioResponseInfo {
+    /// Converts to the same representation as CSV info part, so that it can be processed uniformly.
+    fn from(json: &JsonStatementInfo) -> Self {
+        let mut info = FioResponseInfo::from(HashMap::new());
+        info.insert(INFO_ACCOUNT_ID, &json.account_id);
+        info.insert(INFO_BANK_ID, &json.bank_id);
+        info.insert(INFO_CURRENCY, &json.currency);
+        info.insert(INFO_IBAN, &json.iban);
+        info.insert(INFO_BIC, &json.bic);
+        info.insert(INFO_OPENING_BALANCE, json.opening_balance);
+        info.insert(INFO_CLOSING_BALANCE, json.closing_balance);
+        info.insert(INFO_DATE_START, fio_date::format_fio_date(&json.date_start));
+        info.insert(INFO_DATE_END, fio_date::format_fio_date(&json.date_end));
+        let optionals = [
+            (INFO_YEAR_LIST, json.year_list.map(u64::from)),
+            (INFO_ID_LIST, json.id_list.map(u64::from)),
+            (INFO_ID_FROM, json.id_from),
+            (INFO_ID_TO, json.id_to),
+            (INFO_ID_LAST_DOWNLOAD, json.id_last_download),
+        ];
+        for (key, value) in optionals {
+            if let Some(value) = value {
+                info.insert(key, value);
+            }
+        }
+        info
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonResponse {
+    account_statement: JsonAccountStatement,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonAccountStatement {
+    info: JsonStatementInfo,
+    #[serde(default)]
+    transaction_list: Option<JsonTransactionList>,
+}
+
+#[derive(Deserialize)]
+struct JsonTransactionList {
+    #[serde(default)]
+    transaction: Vec<HashMap<String, Option<JsonColumn>>>,
+}
+
+/// Single column value, like `{"value":1500.0,"name":"Objem","id":1}`.
+#[derive(Deserialize)]
+struct JsonColumn {
+    value: serde_json::Value,
+    id: u16,
+}
+
+impl JsonColumn {
+    fn text(&self) -> Option<String> {
+        match &self.value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(number) => Some(number_text(number)),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// Integers are kept as they are; floats are rounded to hundredths, because their default
+/// representation may use an exponent (`1e21`, `1e-7`) which [`crate::Amount`] does not accept.
+fn number_text(number: &serde_json::Number) -> String {
+    match number.as_f64() {
+        Some(value) if number.is_f64() => format!("{:.2}", (value * 100.0).round() / 100.0),
+        _ => number.to_string(),
+    }
+}
+
+impl JsonStatement {
+    /// Parses the complete JSON document.
+    pub fn parse(bytes: &[u8]) -> crate::Result<Self> {
+        let response: JsonResponse = serde_json::from_slice(bytes)?;
+        let statement = response.account_statement;
+        let transactions = statement.transaction_list
+            .map(|list| list.transaction)
+            .unwrap_or_default()
+            .iter()
+            .map(|tx| {
+                let columns: HashMap<u16, String> = tx.values()
+                    .flatten()
+                    .filter_map(|column| column.text().map(|text| (column.id, text)))
+                    .collect();
+                FioTransactionsRecord::from_columns(&columns)
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self {
+            info: statement.info,
+            transactions,
+        })
+    }
+}
+
+/// JSON amounts are numbers; they are rounded to hundredths and parsed from text.
+mod json_amount {
+    use serde::{Deserialize, Deserializer};
+
+    use crate::Amount;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Amount, D::Error>
+        where D: Deserializer<'de>,
+    {
+        let number = serde_json::Number::deserialize(deserializer)?;
+        super::number_text(&number).parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+mod json_date {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer};
+
+    use crate::csvdata::fio_date;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+        where D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        fio_date::parse_iso_date(&s)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::csvdata::TxType;
+    use crate::error::Result;
+    use crate::{Amount, FioResponseInfo};
+
+    use super::JsonStatement;
+
+    const SAMPLE1: &str = r#"{"accountStatement":{"info":{"accountId":"2345678901","bankId":"2010","currency":"CZK",
+"iban":"CZ6220100000002345678901","bic":"FIOBCZPPXXX","openingBalance":4789.51,"closingBalance":19753.26,
+"dateStart":"2021-06-01+0200","dateEnd":"2021-06-30+0200","yearList":null,"idList":null,
+"idFrom":23771345451,"idTo":23794028126,"idLastDownload":null},
+"transactionList":{"transaction":[
+{"column22":{"value":23771345451,"name":"ID pohybu","id":22},"column0":{"value":"2021-06-01+0200","name":"Datum","id":0},
+"column1":{"value":1500.0,"name":"Objem","id":1},"column14":{"value":"CZK","name":"Měna","id":14},
+"column2":{"value":"2702016516","name":"Protiúčet","id":2},"column10":{"value":"Novák, Jan","name":"Název protiúčtu","id":10},
+"column3":{"value":"2010","name":"Kód banky","id":3},"column12":{"value":"Fio banka, a.s.","name":"Název banky","id":12},
+"column4":null,"column5":{"value":"123","name":"VS","id":5},"column6":null,"column7":{"value":"Novák, Jan","name":"Uživatelská identifikace","id":7},
+"column16":{"value":"nájem","name":"Zpráva pro příjemce","id":16},"column8":{"value":"Příjem převodem uvnitř banky","name":"Typ","id":8},
+"column9":null,"column18":null,"column25":null,"column26":null,"column17":{"value":26010123456,"name":"ID pokynu","id":17}},
+{"column22":{"value":23794028126,"name":"ID pohybu","id":22},"column0":{"value":"2021-06-30+0200","name":"Datum","id":0},
+"column1":{"value":-102.93,"name":"Objem","id":1},"column14":{"value":"CZK","name":"Měna","id":14},
+"column8":{"value":"Platba kartou","name":"Typ","id":8},"column17":null}
+]}}}"#;
+
+    #[test]
+    fn test_parse_json() -> Result<()> {
+        let statement = JsonStatement::parse(SAMPLE1.as_bytes())?;
+        assert_eq!("2345678901", statement.info.account_id);
+        assert_eq!(Amount::from_hundredths(478951), statement.info.opening_balance);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), statement.info.date_end);
+        assert_eq!(Some(23794028126), statement.info.id_to);
+        assert_eq!(None, statement.info.id_last_download);
+        assert_eq!(2, statement.transactions.len());
+
+        let tx = &statement.transactions[0];
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), tx.date);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!("2702016516", tx.b_account);
+        assert_eq!("123", tx.vs);
+        assert_eq!("", tx.ks);
+        assert!(matches!(tx.tx_type, TxType::FioIncome));
+        assert_eq!(Some(26010123456), tx.id_command);
+
+        let tx = &statement.transactions[1];
+        assert_eq!(Amount::from_hundredths(-10293), tx.value);
+        assert!(matches!(tx.tx_type, TxType::X11));
+        assert_eq!(None, tx.id_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_float_amounts() -> Result<()> {
+        let json = SAMPLE1
+            .replace("\"openingBalance\":4789.51", "\"openingBalance\":1e-7")
+            .replace("\"value\":1500.0", "\"value\":1.5e3")
+            .replace("\"value\":-102.93", "\"value\":-102.929999");
+        let statement = JsonStatement::parse(json.as_bytes())?;
+        assert_eq!(Amount::from_hundredths(0), statement.info.opening_balance);
+        assert_eq!(Amount::from_hundredths(150000), statement.transactions[0].value);
+        assert_eq!(Amount::from_hundredths(-10293), statement.transactions[1].value);
+        assert!(JsonStatement::parse(SAMPLE1.replace("4789.51", "1e21").as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_conversion() -> Result<()> {
+        let statement = JsonStatement::parse(SAMPLE1.as_bytes())?;
+        let info = FioResponseInfo::from(&statement.info);
+        assert_eq!("CZ6220100000002345678901", info.iban()?);
+        assert_eq!(Amount::from_hundredths(1975326), info.closing_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), info.date_start()?);
+        assert_eq!("23771345451", info.id_from()?);
+        Ok(())
+    }
+}
@@ -1,11 +1,29 @@
 //! FIO API library
 
-pub use client::{FioClient, FioClientWithImport};
+pub use account::{CzAccount, ParseAccountError};
+pub use amount::{Amount, ParseAmountError};
+pub use camt053::{Camt053Entry, Camt053Statement};
+pub use csvdata::{FioTransactionsRecord, TxType};
+pub use client::{FioClient, FioClientBuilder, FioClientWithImport, FIOAPI_URL_BASE};
 pub use error::{FioError, Result};
 pub use export::{FioExportReq, ReportFormat, TxFormat};
-pub use import::{DetailsOfCharges, DomesticPayment, DomesticSymbolsBuilder, DomesticTransaction, ForeignPayment, ForeignTransaction, PaymentBuilder, PaymentType, T2Payment, T2Transaction};
+pub use jsondata::{JsonStatement, JsonStatementInfo};
+pub use gpc::GpcStatement;
+pub use iban::{Bic, Iban, ParseBicError, ParseIbanError};
+pub use import::{AbroadTransaction, DetailsOfCharges, DomesticPayment, DomesticSymbolsBuilder, DomesticTransaction, ForeignPayment, ForeignTransaction, Payment, PaymentBuilder, PaymentType, PaymentViolation, T2Payment, T2Transaction};
+pub use import_result::{ImportMessage, ImportResult, ImportStatus, ImportSum};
+#[cfg(feature = "mock")]
+pub use mock::{MockAccount, MockFioServer};
+pub use mt940::Mt940Statement;
+pub use ofx::OfxStatement;
+pub use order::{Beneficiary, DomesticOrder, ForeignOrder, T2Order};
 pub use period::FioPeriod;
+pub use pool::FioTokenPool;
+pub use rate_limit::{FileRateLimitStore, MemoryRateLimitStore, RateLimitStore};
+pub use xmldata::XmlStatement;
 pub use response::{FioResponse, FioResponseInfo};
+pub use retry::RetryPolicy;
+pub use statement::{Statement, StatementParser};
 
 type FioDatum = String;
 
@@ -19,11 +37,28 @@ mod tests {
     }
 }
 
+mod account;
+mod amount;
 mod csvdata;
+mod jsondata;
+mod xmldata;
+mod mt940;
+mod camt053;
+mod gpc;
+mod iban;
+mod ofx;
+mod statement;
 mod export;
 mod import;
+mod import_result;
+mod order;
 mod error;
 mod response;
 mod tiny_xml;
 mod period;
+mod pool;
+mod rate_limit;
+mod retry;
 mod client;
+#[cfg(feature = "mock")]
+mod mock;
@@ -0,0 +1,881 @@
+//! In-process stand-in of Fio API, for testing applications without access to the bank.
+//!
+//! Available with feature `mock`. The server listens on a random local port and serves
+//! the export endpoints (doc/5.2) in CSV and Fio XML formats, and the import endpoint (doc/6.1),
+//! for the accounts it was started with. Like the real API, it answers:
+//! - `409 Conflict` when the same token is used again within the request rate (doc/8.3)
+//! - `500` with XML error body for a statement that does not exist
+//! - `500` with a plain text message for an unknown token (doc/8.4)
+//!
+//! ```no_run
+//! # async fn run() -> fio_api::Result<()> {
+//! use fio_api::{FioExportReq, MockAccount, MockFioServer, TxFormat};
+//!
+//! let server = MockFioServer::start(vec![MockAccount::seeded("TOKEN")]).await?;
+//! let fio = server.client("TOKEN")?;
+//! let statement = fio.export_statement(FioExportReq::Last { format: TxFormat::Csv }).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use roxmltree::{Document, Node};
+use tokio::sync::oneshot;
+use tokio::time::{Duration, Instant};
+
+use crate::client::REQUEST_RATE;
+use crate::csvdata::{column, fio_date, FioTransactionsRecord, TxType};
+use crate::response::*;
+use crate::tiny_xml::TinyXml;
+use crate::{Amount, FioClient};
+
+const BANK_ID: &str = "2010";
+const BIC: &str = "FIOBCZPPXXX";
+const XML_CONTENT_TYPE: &str = "text/xml;charset=UTF-8";
+const CSV_CONTENT_TYPE: &str = "text/csv;charset=UTF-8";
+
+/// Account served by [MockFioServer], identified by its token.
+#[derive(Debug, Clone)]
+pub struct MockAccount {
+    token: String,
+    account_id: String,
+    currency: String,
+    /// balance before the first transaction
+    opening_balance: Amount,
+    transactions: Vec<FioTransactionsRecord>,
+    merchant_transactions: Vec<FioTransactionsRecord>,
+    /// official statements: (year, id) -> date range
+    statements: BTreeMap<(u16, u8), (NaiveDate, NaiveDate)>,
+    read_only: bool,
+}
+
+impl MockAccount {
+    /// Empty account, with no transactions.
+    pub fn new(token: &str, account_id: &str, currency: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            account_id: account_id.to_string(),
+            currency: currency.to_string(),
+            opening_balance: Amount::ZERO,
+            transactions: vec![],
+            merchant_transactions: vec![],
+            statements: BTreeMap::new(),
+            read_only: false,
+        }
+    }
+
+    /// CZK account `2345678901/2010` with a few transactions in June and July 2021,
+    /// official statements `2021/6` and `2021/7`, and one card transaction of merchant.
+    pub fn seeded(token: &str) -> Self {
+        let date = |m, d| NaiveDate::from_ymd_opt(2021, m, d).unwrap();
+        let mut income = record(23771345451, date(6, 1), Amount::from_hundredths(150000), "CZK", TxType::FioIncome);
+        income.b_account = "2702016516".to_string();
+        income.b_bankid = BANK_ID.to_string();
+        income.b_account_name = "Novák, Jan".to_string();
+        income.b_bank_name = Some("Fio banka, a.s.".to_string());
+        income.vs = "123".to_string();
+        income.custom_id = "Novák, Jan".to_string();
+        income.message = "nájem".to_string();
+        income.id_command = Some(26010123456);
+        let mut payment = record(23780000001, date(6, 15), Amount::from_hundredths(-25000), "CZK", TxType::X09);
+        payment.b_account = "1234567890".to_string();
+        payment.b_bankid = "0800".to_string();
+        payment.b_bank_name = Some("Česká spořitelna, a.s.".to_string());
+        payment.ks = "0308".to_string();
+        payment.vs = "2021006".to_string();
+        payment.message = "faktura 2021006".to_string();
+        payment.who = "Novák, Jan".to_string();
+        payment.id_command = Some(26010200001);
+        let mut card = record(23794028126, date(6, 30), Amount::from_hundredths(-10293), "CZK", TxType::X11);
+        card.custom_id = "Nákup: ALBERT, Praha".to_string();
+        card.note = "102.93 CZK".to_string();
+        let mut salary = record(23801000001, date(7, 2), Amount::from_hundredths(500000), "CZK", TxType::X10);
+        salary.b_account = "1234567890".to_string();
+        salary.b_bankid = "0800".to_string();
+        salary.message = "mzda 06/2021".to_string();
+        let mut merchant = record(23790000001, date(6, 20), Amount::from_hundredths(49900), "CZK", TxType::X10);
+        merchant.message = "karta *1234".to_string();
+        Self::new(token, "2345678901", "CZK")
+            .opening_balance(Amount::from_hundredths(478951))
+            .record(income)
+            .record(payment)
+            .record(card)
+            .record(salary)
+            .merchant_record(merchant)
+            .statement(2021, 6, date(6, 1), date(6, 30))
+            .statement(2021, 7, date(7, 1), date(7, 31))
+    }
+
+    pub fn opening_balance(mut self, amount: Amount) -> Self {
+        self.opening_balance = amount;
+        self
+    }
+
+    /// Adds a simple transfer; transactions should be added in chronological order.
+    pub fn transaction(self, id: u64, date: NaiveDate, amount: Amount, message: &str) -> Self {
+        let tx_type = if amount.is_negative() { TxType::X09 } else { TxType::X10 };
+        let mut tx = record(id, date, amount, &self.currency, tx_type);
+        tx.message = message.to_string();
+        self.record(tx)
+    }
+
+    /// Adds a transaction with all details.
+    pub fn record(mut self, record: FioTransactionsRecord) -> Self {
+        self.transactions.push(record);
+        self
+    }
+
+    /// Adds a card transaction of merchant, see [crate::FioExportReq::Merchant].
+    pub fn merchant_record(mut self, record: FioTransactionsRecord) -> Self {
+        self.merchant_transactions.push(record);
+        self
+    }
+
+    /// Declares an official statement, consisting of transactions in given date range.
+    pub fn statement(mut self, year: u16, id: u8, date_start: NaiveDate, date_end: NaiveDate) -> Self {
+        self.statements.insert((year, id), (date_start, date_end));
+        self
+    }
+
+    /// Token of this account only allows reading; imports are rejected with `errorCode` 2001.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    fn iban(&self) -> String {
+        let (prefix, number) = match self.account_id.split_once('-') {
+            Some((prefix, number)) => (prefix, number),
+            None => ("", self.account_id.as_str()),
+        };
+        let bban = format!("{}{:0>6}{:0>10}", BANK_ID, prefix, number);
+        // check digits of "CZ00" appended as digits (C=12, Z=35)
+        let remainder = format!("{}123500", bban).bytes()
+            .fold(0_u32, |acc, b| (acc * 10 + (b - b'0') as u32) % 97);
+        format!("CZ{:02}{}", 98 - remainder, bban)
+    }
+}
+
+fn record(id: u64, date: NaiveDate, value: Amount, currency: &str, tx_type: TxType) -> FioTransactionsRecord {
+    FioTransactionsRecord {
+        id_tx: id,
+        date,
+        value,
+        currency: currency.to_string(),
+        b_account: String::new(),
+        b_account_name: String::new(),
+        b_bankid: String::new(),
+        b_bank_name: None,
+        ks: String::new(),
+        vs: String::new(),
+        ss: String::new(),
+        custom_id: String::new(),
+        message: String::new(),
+        tx_type,
+        who: String::new(),
+        note: String::new(),
+        comment: String::new(),
+        bic: String::new(),
+        id_command: None,
+    }
+}
+
+struct AccountState {
+    account: MockAccount,
+    /// zarážka: id of the last downloaded transaction
+    last_id: Option<u64>,
+    imported: Vec<String>,
+    last_request: Option<Instant>,
+}
+
+struct MockState {
+    accounts: HashMap<String, AccountState>,
+    request_rate: Duration,
+    next_instruction: u64,
+}
+
+/// Running mock server; it stops when dropped.
+pub struct MockFioServer {
+    url_base: String,
+    request_rate: Duration,
+    state: Arc<Mutex<MockState>>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockFioServer {
+    /// Starts the server, enforcing the same request rate as the real API.
+    pub async fn start(accounts: Vec<MockAccount>) -> std::io::Result<Self> {
+        Self::start_with_request_rate(accounts, REQUEST_RATE).await
+    }
+
+    /// Starts the server with custom request rate, to keep tests fast.
+    pub async fn start_with_request_rate(accounts: Vec<MockAccount>, request_rate: Duration) -> std::io::Result<Self> {
+        let accounts = accounts.into_iter()
+            .map(|account| (account.token.clone(), AccountState { account, last_id: None, imported: vec![], last_request: None }))
+            .collect();
+        let state = Arc::new(Mutex::new(MockState { accounts, request_rate, next_instruction: 1801400001 }));
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request)))
+            }
+        });
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_rx.await.ok();
+            });
+        tokio::spawn(server);
+        log::debug!("Fio API mock listening on {}", addr);
+        Ok(Self {
+            url_base: format!("http://{}/ib_api/rest", addr),
+            request_rate,
+            state,
+            _shutdown: shutdown,
+        })
+    }
+
+    /// Base URL to be used with [crate::FioClientBuilder::url_base].
+    pub fn url_base(&self) -> &str {
+        &self.url_base
+    }
+
+    /// Client configured to talk to this server.
+    pub fn client(&self, token: &str) -> crate::Result<FioClient> {
+        FioClient::builder(token)
+            .url_base(&self.url_base)
+            .request_rate(self.request_rate)
+            .build()
+    }
+
+    /// XML documents received by the import endpoint for given token, in the order of arrival.
+    pub fn imported(&self, token: &str) -> Vec<String> {
+        self.state.lock().unwrap()
+            .accounts.get(token)
+            .map(|account| account.imported.clone())
+            .unwrap_or_default()
+    }
+}
+
+async fn handle(state: Arc<Mutex<MockState>>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let content_type = request.headers().get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(_) => return Ok(status(StatusCode::BAD_REQUEST)),
+    };
+    log::trace!("mock: {} {}", method, path);
+    let segments: Vec<&str> = path.trim_start_matches("/ib_api/rest/")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut state = state.lock().unwrap();
+    let response = match (method, segments.as_slice()) {
+        (Method::POST, ["import"]) => {
+            let fields = multipart_fields(&content_type, &body);
+            match fields.get("token") {
+                None => status(StatusCode::NOT_FOUND),
+                Some(token) => state.with_account(token, |account, state| {
+                    let xml = fields.get("file").map(String::as_str).unwrap_or_default();
+                    account.imported.push(xml.to_string());
+                    let response = import(&account.account, xml, state.next_instruction);
+                    state.next_instruction += 1;
+                    response
+                }),
+            }
+        }
+        (Method::GET, [command, token, params @ ..]) => {
+            let command = command.to_string();
+            let params: Vec<String> = params.iter().map(|s| s.to_string()).collect();
+            state.with_account(token, |account, _| export(account, &command, &params))
+        }
+        _ => status(StatusCode::NOT_FOUND),
+    };
+    Ok(response)
+}
+
+impl MockState {
+    /// Checks token and request rate before calling the handler.
+    fn with_account<F>(&mut self, token: &str, handler: F) -> Response<Body>
+        where F: FnOnce(&mut AccountState, &mut MockRequestState) -> Response<Body>,
+    {
+        let request_rate = self.request_rate;
+        let account = match self.accounts.get_mut(token) {
+            // doc/8.4: unknown token results in plain error without the XML details
+            None => {
+                let mut response = body("text/plain;charset=UTF-8", "Neexistující nebo neaktivní token".to_string());
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                return response;
+            }
+            Some(account) => account,
+        };
+        let now = Instant::now();
+        if let Some(last_request) = account.last_request {
+            if now < last_request + request_rate {
+                return status(StatusCode::CONFLICT);
+            }
+        }
+        account.last_request = Some(now);
+        let mut request_state = MockRequestState { next_instruction: self.next_instruction };
+        let response = handler(account, &mut request_state);
+        self.next_instruction = request_state.next_instruction;
+        response
+    }
+}
+
+/// Server-wide state available to handlers.
+struct MockRequestState {
+    next_instruction: u64,
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+fn body(content_type: &str, body: String) -> Response<Body> {
+    Response::builder()
+        .header(hyper::header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// The XML error body, as sent by Fio API with status 500.
+fn xml_error(code: u16, message: &str) -> Response<Body> {
+    let xml = format!(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<response xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="https://www.fio.cz/schema/response.xsd">
+	<result>
+		<errorCode>{}</errorCode>
+		<status>error</status>
+		<message>{}</message>
+		<detail></detail>
+	</result>
+</response>"#, code, message);
+    let mut response = body(XML_CONTENT_TYPE, xml);
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+/// doc/5.2: export endpoints; `params` are the URL segments following the token.
+fn export(state: &mut AccountState, command: &str, params: &[String]) -> Response<Body> {
+    let account = &state.account;
+    match (command, params) {
+        ("periods", [date_start, date_end, file]) | ("merchant", [date_start, date_end, file]) => {
+            let (date_start, date_end) = match (parse_date(date_start), parse_date(date_end)) {
+                (Some(date_start), Some(date_end)) => (date_start, date_end),
+                _ => return status(StatusCode::NOT_FOUND),
+            };
+            let source = if command == "merchant" { &account.merchant_transactions } else { &account.transactions };
+            let transactions: Vec<_> = source.iter()
+                .filter(|tx| date_start <= tx.date && tx.date <= date_end)
+                .collect();
+            render(account, file, date_start, date_end, &transactions)
+        }
+        ("by-id", [year, id, file]) => {
+            let key = match (year.parse(), id.parse()) {
+                (Ok(year), Ok(id)) => (year, id),
+                _ => return status(StatusCode::NOT_FOUND),
+            };
+            match account.statements.get(&key) {
+                None => xml_error(21, "Výpis neexistuje"),
+                Some(&(date_start, date_end)) => {
+                    let transactions: Vec<_> = account.transactions.iter()
+                        .filter(|tx| date_start <= tx.date && tx.date <= date_end)
+                        .collect();
+                    render(account, file, date_start, date_end, &transactions)
+                }
+            }
+        }
+        ("last", [file]) => {
+            let last_id = state.last_id;
+            let transactions: Vec<_> = account.transactions.iter()
+                .filter(|tx| last_id.map_or(true, |last_id| tx.id_tx > last_id))
+                .collect();
+            let date_start = transactions.first().map_or_else(today, |tx| tx.date);
+            let date_end = transactions.last().map_or_else(today, |tx| tx.date);
+            let response = render(account, file, date_start, date_end, &transactions);
+            if response.status().is_success() {
+                if let Some(tx) = transactions.last() {
+                    state.last_id = Some(tx.id_tx);
+                }
+            }
+            response
+        }
+        ("set-last-id", [id]) => match id.parse() {
+            Ok(id) => {
+                state.last_id = Some(id);
+                status(StatusCode::OK)
+            }
+            Err(_) => status(StatusCode::NOT_FOUND),
+        },
+        ("set-last-date", [date]) => match parse_date(date) {
+            Some(date) => {
+                state.last_id = account.transactions.iter()
+                    .rev()
+                    .find(|tx| tx.date < date)
+                    .map(|tx| tx.id_tx);
+                status(StatusCode::OK)
+            }
+            None => status(StatusCode::NOT_FOUND),
+        },
+        ("lastStatement", [_]) => match account.statements.keys().last() {
+            None => body(CSV_CONTENT_TYPE, String::new()),
+            Some((year, id)) => body(CSV_CONTENT_TYPE, format!("{},{}", year, id)),
+        },
+        _ => status(StatusCode::NOT_FOUND),
+    }
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Statement in the format given by the file name (`transactions.csv` or `transactions.xml`).
+fn render(account: &MockAccount, file: &str, date_start: NaiveDate, date_end: NaiveDate, transactions: &[&FioTransactionsRecord]) -> Response<Body> {
+    let opening_balance = account.opening_balance + account.transactions.iter()
+        .filter(|tx| tx.date < date_start)
+        .map(|tx| tx.value)
+        .sum();
+    let closing_balance = opening_balance + transactions.iter().map(|tx| tx.value).sum();
+    let info = StatementInfo { account, opening_balance, closing_balance, date_start, date_end, transactions };
+    let rendered = match file {
+        "transactions.csv" => render_csv(&info).map(|text| body(CSV_CONTENT_TYPE, text)),
+        "transactions.xml" => render_xml(&info).map(|text| body(XML_CONTENT_TYPE, text)),
+        // other formats are not supported by the mock
+        _ => return status(StatusCode::NOT_FOUND),
+    };
+    rendered.unwrap_or_else(|e| {
+        log::error!("mock: failed to render statement: {}", e);
+        status(StatusCode::INTERNAL_SERVER_ERROR)
+    })
+}
+
+struct StatementInfo<'a> {
+    account: &'a MockAccount,
+    opening_balance: Amount,
+    closing_balance: Amount,
+    date_start: NaiveDate,
+    date_end: NaiveDate,
+    transactions: &'a [&'a FioTransactionsRecord],
+}
+
+fn render_csv(info: &StatementInfo) -> crate::Result<String> {
+    let account = info.account;
+    let mut text = String::from("\u{feff}");
+    let mut line = |key: &str, value: String| {
+        text.push_str(&format!("{};{}\n", key, value));
+    };
+    line(INFO_ACCOUNT_ID, account.account_id.clone());
+    line(INFO_BANK_ID, BANK_ID.to_string());
+    line(INFO_CURRENCY, account.currency.clone());
+    line(INFO_IBAN, account.iban());
+    line(INFO_BIC, BIC.to_string());
+    line(INFO_OPENING_BALANCE, info.opening_balance.to_fio_string());
+    line(INFO_CLOSING_BALANCE, info.closing_balance.to_fio_string());
+    line(INFO_DATE_START, fio_date::format_fio_date(&info.date_start));
+    line(INFO_DATE_END, fio_date::format_fio_date(&info.date_end));
+    if let (Some(first), Some(last)) = (info.transactions.first(), info.transactions.last()) {
+        line(INFO_ID_FROM, first.id_tx.to_string());
+        line(INFO_ID_TO, last.id_tx.to_string());
+    }
+    text.push('\n');
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(vec![]);
+    for tx in info.transactions {
+        writer.serialize(tx)?;
+    }
+    let data = writer.into_inner()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    text.push_str(&String::from_utf8_lossy(&data));
+    Ok(text)
+}
+
+fn render_xml(info: &StatementInfo) -> crate::Result<String> {
+    let account = info.account;
+    let iso_date = |date: &NaiveDate| format!("{}+02:00", date);
+    let mut doc = TinyXml::new()?;
+    doc.open("AccountStatement")?;
+    doc.open("Info")?;
+    doc.simple(INFO_ACCOUNT_ID, &account.account_id)?;
+    doc.simple(INFO_BANK_ID, BANK_ID)?;
+    doc.simple(INFO_CURRENCY, &account.currency)?;
+    doc.simple(INFO_IBAN, &account.iban())?;
+    doc.simple(INFO_BIC, BIC)?;
+    doc.simple(INFO_OPENING_BALANCE, &info.opening_balance.to_string())?;
+    doc.simple(INFO_CLOSING_BALANCE, &info.closing_balance.to_string())?;
+    doc.simple(INFO_DATE_START, &iso_date(&info.date_start))?;
+    doc.simple(INFO_DATE_END, &iso_date(&info.date_end))?;
+    if let (Some(first), Some(last)) = (info.transactions.first(), info.transactions.last()) {
+        doc.simple(INFO_ID_FROM, &first.id_tx.to_string())?;
+        doc.simple(INFO_ID_TO, &last.id_tx.to_string())?;
+    }
+    doc.close()?;
+    doc.open("TransactionList")?;
+    for tx in info.transactions {
+        doc.open("Transaction")?;
+        let columns = [
+            (column::ID_TX, "ID pohybu", tx.id_tx.to_string()),
+            (column::DATE, "Datum", iso_date(&tx.date)),
+            (column::VALUE, "Objem", tx.value.to_string()),
+            (column::CURRENCY, "Měna", tx.currency.clone()),
+            (column::B_ACCOUNT, "Protiúčet", tx.b_account.clone()),
+            (column::B_ACCOUNT_NAME, "Název protiúčtu", tx.b_account_name.clone()),
+            (column::B_BANKID, "Kód banky", tx.b_bankid.clone()),
+            (column::B_BANK_NAME, "Název banky", tx.b_bank_name.clone().unwrap_or_default()),
+            (column::KS, "KS", tx.ks.clone()),
+            (column::VS, "VS", tx.vs.clone()),
+            (column::SS, "SS", tx.ss.clone()),
+            (column::CUSTOM_ID, "Uživatelská identifikace", tx.custom_id.clone()),
+            (column::MESSAGE, "Zpráva pro příjemce", tx.message.clone()),
+            (column::TX_TYPE, "Typ", tx.tx_type.as_str().to_string()),
+            (column::WHO, "Provedl", tx.who.clone()),
+            (column::NOTE, "Upřesnění", tx.note.clone()),
+            (column::COMMENT, "Komentář", tx.comment.clone()),
+            (column::BIC, "BIC", tx.bic.clone()),
+            (column::ID_COMMAND, "ID pokynu", tx.id_command.map(|id| id.to_string()).unwrap_or_default()),
+        ];
+        for (id, name, value) in &columns {
+            if !value.is_empty() {
+                doc.simple_attrs(&format!("column_{}", id), &[("name", name), ("id", &id.to_string())], value)?;
+            }
+        }
+        doc.close()?;
+    }
+    Ok(doc.into_xml()?)
+}
+
+/// Extracts text fields of `multipart/form-data` body.
+fn multipart_fields(content_type: &str, body: &[u8]) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let boundary = match content_type.split("boundary=").nth(1) {
+        None => return fields,
+        Some(boundary) => boundary.trim_matches('"'),
+    };
+    let body = String::from_utf8_lossy(body);
+    for part in body.split(&format!("--{}", boundary)) {
+        let (headers, content) = match part.split_once("\r\n\r\n") {
+            None => continue,
+            Some(split) => split,
+        };
+        let name = headers.split("name=\"").nth(1)
+            .and_then(|s| s.split('"').next());
+        if let Some(name) = name {
+            let content = content.strip_suffix("\r\n").unwrap_or(content);
+            fields.insert(name.to_string(), content.to_string());
+        }
+    }
+    fields
+}
+
+/// doc/6.1: validates the orders and builds the `responseImport` document.
+fn import(account: &MockAccount, xml: &str, id_instruction: u64) -> Response<Body> {
+    let result = match Document::parse(xml) {
+        Err(e) => ImportOutcome::rejected(11, "error", &format!("syntaktická chyba: {}", e)),
+        Ok(_) if account.read_only => ImportOutcome::rejected(2001, "error", "Vaše uživatelské oprávnění nepovoluje podání pokynu z tohoto účtu"),
+        Ok(doc) => {
+            let orders: Vec<Node> = doc.descendants()
+                .find(|n| n.has_tag_name("Orders"))
+                .map(|orders| orders.children().filter(Node::is_element).collect())
+                .unwrap_or_default();
+            if orders.is_empty() {
+                ImportOutcome::rejected(12, "error", "prázdný import - v souboru nejsou žádné příkazy")
+            } else {
+                ImportOutcome::validate(account, &orders, id_instruction)
+            }
+        }
+    };
+    match result.to_xml() {
+        Ok(xml) => body(XML_CONTENT_TYPE, xml),
+        Err(e) => {
+            log::error!("mock: failed to render import response: {}", e);
+            status(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+struct ImportOutcome {
+    error_code: u16,
+    status: &'static str,
+    message: String,
+    id_instruction: Option<u64>,
+    sums: BTreeMap<String, Amount>,
+    /// per order: (status, errorCode, message)
+    details: Vec<(&'static str, u16, String)>,
+}
+
+impl ImportOutcome {
+    fn rejected(error_code: u16, status: &'static str, message: &str) -> Self {
+        Self {
+            error_code,
+            status,
+            message: message.to_string(),
+            id_instruction: None,
+            sums: BTreeMap::new(),
+            details: vec![],
+        }
+    }
+
+    fn validate(account: &MockAccount, orders: &[Node], id_instruction: u64) -> Self {
+        let mut sums = BTreeMap::new();
+        let mut details = vec![];
+        for order in orders {
+            let field = |name: &str| order.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+                .filter(|s| !s.is_empty());
+            let mut required = vec!["accountFrom", "currency", "amount", "accountTo", "date"];
+            match order.tag_name().name() {
+                "DomesticTransaction" => required.push("bankCode"),
+                "T2Transaction" | "ForeignTransaction" => required.push("bic"),
+                other => {
+                    details.push(("error", 1, format!("Neznámý typ příkazu '{}'", other)));
+                    continue;
+                }
+            }
+            if let Some(missing) = required.iter().find(|&&name| field(name).is_none()) {
+                details.push(("error", 1, format!("Chybí povinná položka '{}'", missing)));
+                continue;
+            }
+            if field("accountFrom") != Some(account.account_id.as_str()) {
+                details.push(("error", 1, "Účet příkazce neodpovídá tokenu".to_string()));
+                continue;
+            }
+            match field("amount").map(str::parse::<Amount>) {
+                Some(Ok(amount)) if amount.is_positive() => {
+                    let currency = field("currency").unwrap_or_default().to_string();
+                    *sums.entry(currency).or_insert(Amount::ZERO) += amount;
+                    details.push(("ok", 0, "OK".to_string()));
+                }
+                _ => details.push(("error", 1, "Neplatná částka".to_string())),
+            }
+        }
+        if details.iter().all(|(status, _, _)| *status == "ok") {
+            Self { error_code: 0, status: "ok", message: String::new(), id_instruction: Some(id_instruction), sums, details }
+        } else {
+            Self { error_code: 1, status: "error", message: "nalezené chyby při kontrole příkazů".to_string(), id_instruction: None, sums, details }
+        }
+    }
+
+    fn to_xml(&self) -> std::io::Result<String> {
+        let mut doc = TinyXml::new()?;
+        doc.open_attrs("responseImport", &[
+            ("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"),
+            ("xsi:noNamespaceSchemaLocation", "http://www.fio.cz/schema/responseImport.xsd"),
+        ])?;
+        doc.open("result")?;
+        doc.simple("errorCode", &self.error_code.to_string())?;
+        if let Some(id_instruction) = self.id_instruction {
+            doc.simple("idInstruction", &id_instruction.to_string())?;
+        }
+        doc.simple("status", self.status)?;
+        doc.simple("message", &self.message)?;
+        if !self.sums.is_empty() {
+            doc.open("sums")?;
+            for (currency, debet) in &self.sums {
+                doc.open_attrs("sum", &[("id", currency)])?;
+                doc.simple("sumCredit", "0")?;
+                doc.simple("sumDebet", &debet.to_string())?;
+                doc.close()?;
+            }
+            doc.close()?;
+        }
+        doc.close()?;
+        if !self.details.is_empty() {
+            doc.open("ordersDetails")?;
+            for (index, (status, error_code, message)) in self.details.iter().enumerate() {
+                doc.open_attrs("detail", &[("id", &(index + 1).to_string())])?;
+                doc.open("messages")?;
+                doc.simple_attrs("message", &[("status", status), ("errorCode", &error_code.to_string())], message)?;
+                doc.close()?;
+                doc.close()?;
+            }
+        }
+        doc.into_xml()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use tokio::time::Duration;
+
+    use crate::{Amount, FioError, FioExportReq, ReportFormat, TxFormat};
+
+    use super::{MockAccount, MockFioServer};
+
+    const TOKEN: &str = "TEST-TOKEN";
+
+    async fn start() -> MockFioServer {
+        MockFioServer::start_with_request_rate(vec![MockAccount::seeded(TOKEN)], Duration::from_millis(20))
+            .await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_periods_csv_and_xml() -> crate::Result<()> {
+        let server = start().await;
+        let fio = server.client(TOKEN)?;
+        let date_start = NaiveDate::from_ymd_opt(2021, 6, 1).unwrap();
+        let date_end = NaiveDate::from_ymd_opt(2021, 6, 30).unwrap();
+        let csv = fio.export_statement(FioExportReq::Periods { date_start, date_end, format: TxFormat::Csv }).await?;
+        let xml = fio.export_statement(FioExportReq::Periods { date_start, date_end, format: TxFormat::FioXml }).await?;
+        for statement in [csv, xml] {
+            assert_eq!("2345678901", statement.info.account_id()?);
+            assert_eq!(Amount::from_hundredths(478951), statement.info.opening_balance()?);
+            assert_eq!(Amount::from_hundredths(593658), statement.info.closing_balance()?);
+            assert_eq!(3, statement.transactions.len());
+            assert_eq!("Novák, Jan", statement.transactions[0].counter_account_name());
+            let counter_account = statement.transactions[0].counter_account_cz().expect("valid account");
+            assert_eq!("2702016516/2010", counter_account.to_string());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_statements() -> crate::Result<()> {
+        let server = start().await;
+        let fio = server.client(TOKEN)?;
+        let statement = fio.export_statement(FioExportReq::ById { year: 2021, id: 7, format: ReportFormat::Csv }).await?;
+        assert_eq!(1, statement.transactions.len());
+        assert_eq!(Amount::from_hundredths(593658), statement.info.opening_balance()?);
+        let missing = fio.export_statement(FioExportReq::ById { year: 2021, id: 8, format: ReportFormat::Csv }).await;
+        assert!(matches!(missing, Err(FioError::ReportDoesNotExist)));
+        let last = fio.export(FioExportReq::LastStatement).await?.text().await?;
+        assert_eq!("2021,7", last);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_last_and_set_last_id() -> crate::Result<()> {
+        let server = start().await;
+        let fio = server.client(TOKEN)?;
+        let last = fio.export_statement(FioExportReq::Last { format: TxFormat::Csv }).await?;
+        assert_eq!(4, last.transactions.len());
+        let last = fio.export_statement(FioExportReq::Last { format: TxFormat::Csv }).await?;
+        assert_eq!(0, last.transactions.len());
+        fio.export(FioExportReq::SetLastId { id: "23780000001".to_string() }).await?;
+        let last = fio.export_statement(FioExportReq::Last { format: TxFormat::FioXml }).await?;
+        assert_eq!(2, last.transactions.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_high_level_methods() -> crate::Result<()> {
+        let server = start().await;
+        let fio = server.client(TOKEN)?;
+        let date_start = NaiveDate::from_ymd_opt(2021, 6, 1).unwrap();
+        let date_end = NaiveDate::from_ymd_opt(2021, 7, 31).unwrap();
+        assert_eq!(4, fio.transactions_between(date_start, date_end).await?.transactions.len());
+        assert_eq!(1, fio.merchant_transactions(date_start, date_end).await?.transactions.len());
+        let last = fio.last_statement().await?.expect("last statement");
+        assert_eq!((2021, 7), (last.year, last.month));
+        let statement = fio.statement(last.year, last.month).await?;
+        assert_eq!(Amount::from_hundredths(1093658), statement.info.closing_balance()?);
+        assert_eq!(4, fio.new_transactions().await?.transactions.len());
+        assert_eq!(0, fio.new_transactions().await?.transactions.len());
+
+        let server = MockFioServer::start_with_request_rate(vec![MockAccount::new(TOKEN, "2345678901", "CZK")], Duration::from_millis(20)).await?;
+        assert!(server.client(TOKEN)?.last_statement().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conflict_and_invalid_token() -> crate::Result<()> {
+        let server = MockFioServer::start(vec![MockAccount::seeded(TOKEN)]).await?;
+        let url = format!("{}/lastStatement/{}/statement", server.url_base(), TOKEN);
+        let first = reqwest::get(&url).await?;
+        assert_eq!(200, first.status().as_u16());
+        let second = reqwest::get(&url).await?;
+        assert_eq!(409, second.status().as_u16());
+
+        let fio = server.client("UNKNOWN")?;
+        let result = fio.export_statement(FioExportReq::Last { format: TxFormat::Csv }).await;
+        assert!(matches!(result, Err(FioError::InvalidToken)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import() -> crate::Result<()> {
+        use crate::{DomesticTransaction, FioClientWithImport, PaymentBuilder};
+
+        let server = start().await;
+        let fio = FioClientWithImport::new(server.client(TOKEN)?, "2345678901", "CZK");
+        let result = fio.import(fio.new_domestic()
+            .amount(Amount::from_hundredths(10293))
+            .account_to("1234567890", "0800")).await?;
+        assert!(result.is_accepted());
+        assert_eq!(Amount::from_hundredths(10293), result.sums["CZK"].debet);
+        assert_eq!(1, server.imported(TOKEN).len());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let result = fio.import(fio.new_domestic()
+            .amount(Amount::from_hundredths(10293))).await?;
+        assert!(!result.is_accepted());
+        assert_eq!(vec![1], result.failed_orders().map(|(index, _)| index).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_after_export() -> crate::Result<()> {
+        use crate::{DomesticTransaction, FioClientWithImport, PaymentBuilder, RetryPolicy};
+
+        let server = MockFioServer::start_with_request_rate(vec![MockAccount::seeded(TOKEN)], Duration::from_millis(200))
+            .await?;
+        // waits for the slot instead of failing
+        let fio = FioClientWithImport::new(server.client(TOKEN)?, "2345678901", "CZK");
+        fio.fio().export(FioExportReq::LastStatement).await?;
+        let payment = fio.new_domestic()
+            .amount(Amount::from_hundredths(10293))
+            .account_to("1234567890", "0800");
+        assert!(fio.import(payment).await?.is_accepted());
+
+        // ignores the rate limit, and gives up after second refusal
+        let client = crate::FioClient::builder(TOKEN)
+            .url_base(server.url_base())
+            .request_rate(Duration::ZERO)
+            .retry_policy(RetryPolicy::default().max_attempts(2))
+            .build()?;
+        let fio = FioClientWithImport::new(client, "2345678901", "CZK");
+        let payment = fio.new_domestic()
+            .amount(Amount::from_hundredths(10293))
+            .account_to("1234567890", "0800");
+        assert!(matches!(fio.import(payment).await, Err(FioError::InvalidTiming)));
+
+        // would have to wait too long
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let client = crate::FioClient::builder(TOKEN)
+            .url_base(server.url_base())
+            .request_rate(Duration::from_secs(30))
+            .retry_policy(RetryPolicy::default().max_wait(Duration::from_secs(1)))
+            .build()?;
+        client.export(FioExportReq::LastStatement).await.ok();
+        let fio = FioClientWithImport::new(client, "2345678901", "CZK");
+        let payment = fio.new_domestic()
+            .amount(Amount::from_hundredths(10293))
+            .account_to("1234567890", "0800");
+        assert!(matches!(fio.import(payment).await, Err(FioError::InvalidTiming)));
+        Ok(())
+    }
+}
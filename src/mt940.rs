@@ -0,0 +1,349 @@
+//! doc/5.1: MT940 format of official reports (`.sta`), see [crate::ReportFormat::Mt940].
+//!
+//! Supported tags:
+//! - `:20:` reference of the statement
+//! - `:25:` account identification
+//! - `:28C:` statement number / sequence number
+//! - `:60F:`, `:60M:` opening balance
+//! - `:61:` statement line
+//! - `:86:` information to account owner, related to the preceding `:61:`
+//! - `:62F:`, `:62M:` closing balance
+//!
+//! Other tags are ignored.
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::csvdata::{fio_date, fio_decimal, FioTransactionsRecord, TxType};
+use crate::response::*;
+use crate::{FioError, FioResponseInfo};
+
+/// Parsed MT940 statement.
+#[derive(Debug)]
+pub struct Mt940Statement {
+    /// `:20:` transaction reference number
+    pub reference: String,
+    pub info: FioResponseInfo,
+    pub transactions: Vec<FioTransactionsRecord>,
+}
+
+/// Balance as found in tags `:60F:` and `:62F:`, like `C210531CZK4789,51`.
+struct Balance {
+    date: NaiveDate,
+    currency: String,
+    amount: String,
+}
+
+impl Mt940Statement {
+    /// Parses single statement. SWIFT envelope blocks (`{1:...}{4:`) and the trailing `-}` are tolerated.
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let mut reference = None;
+        let mut info = FioResponseInfo::from(HashMap::new());
+        let mut transactions = vec![];
+        let mut pending: Option<FioTransactionsRecord> = None;
+        for (tag, value) in split_tags(text) {
+            match tag {
+                "20" => {
+                    if reference.is_some() {
+                        return Err(invalid("more than one statement in the file"));
+                    }
+                    reference = Some(value.to_string());
+                }
+                "25" => parse_account(value, &mut info),
+                "28C" => {
+                    let number = value.split('/').next().unwrap_or(value).trim();
+                    let number: u64 = number.parse()
+                        .map_err(|_| invalid(&format!("invalid statement number '{}'", number)))?;
+                    info.insert(INFO_ID_LIST, number.to_string());
+                }
+                "60F" | "60M" => {
+                    let balance = parse_balance(value)?;
+                    info.insert(INFO_CURRENCY, &balance.currency);
+                    info.insert(INFO_OPENING_BALANCE, &balance.amount);
+                    info.insert(INFO_DATE_START, fio_date::format_fio_date(&balance.date));
+                }
+                "61" => {
+                    transactions.extend(pending.take());
+                    let currency = info.currency().map(str::to_string).unwrap_or_default();
+                    pending = Some(parse_statement_line(value, currency)?);
+                }
+                "86" => {
+                    if let Some(record) = pending.as_mut() {
+                        record.message = value.lines()
+                            .map(str::trim)
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                    }
+                }
+                "62F" | "62M" => {
+                    transactions.extend(pending.take());
+                    let balance = parse_balance(value)?;
+                    info.insert(INFO_CLOSING_BALANCE, &balance.amount);
+                    info.insert(INFO_DATE_END, fio_date::format_fio_date(&balance.date));
+                    info.insert(INFO_YEAR_LIST, balance.date.format("%Y"));
+                }
+                _ => log::trace!("Ignoring MT940 tag :{}:", tag),
+            }
+        }
+        transactions.extend(pending.take());
+        Ok(Self {
+            reference: reference.ok_or_else(|| invalid("missing tag :20:"))?,
+            info,
+            transactions,
+        })
+    }
+}
+
+fn invalid(message: &str) -> FioError {
+    FioError::InvalidData(format!("MT940: {}", message))
+}
+
+/// Splits the text into `(tag, value)` pairs; multi-line values keep their line breaks.
+fn split_tags(text: &str) -> Vec<(&str, &str)> {
+    let text = match text.find("{4:") {
+        Some(n) => &text[n + 3..],
+        None => text,
+    };
+    let mut result = vec![];
+    let mut current: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "-" || trimmed == "-}" {
+            if let Some((tag, start)) = current.take() {
+                result.push((tag, text[start..offset].trim_end()));
+            }
+        } else if let Some(tag) = line_tag(trimmed) {
+            if let Some((tag, start)) = current.take() {
+                result.push((tag, text[start..offset].trim_end()));
+            }
+            current = Some((tag, offset + tag.len() + 2));
+        }
+        offset += line.len();
+    }
+    if let Some((tag, start)) = current {
+        result.push((tag, text[start..].trim_end()));
+    }
+    result
+}
+
+/// Recognizes line starting with a tag like `:61:` or `:28C:`.
+fn line_tag(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let tag = &rest[..end];
+    let valid = (2..=3).contains(&tag.len())
+        && tag.chars().take(2).all(|c| c.is_ascii_digit())
+        && tag.chars().skip(2).all(|c| c.is_ascii_uppercase());
+    if valid { Some(tag) } else { None }
+}
+
+/// Accepts `account/bank`, `bank/account` or CZ IBAN; when both sides have 4 digits,
+/// the usual Czech `account/bank` order is assumed.
+fn parse_account(value: &str, info: &mut FioResponseInfo) {
+    let value = value.trim();
+    let is_bank_code = |s: &str| s.len() == 4 && s.chars().all(|c| c.is_ascii_digit());
+    match value.split_once('/') {
+        Some((bank, account)) if is_bank_code(bank) && !is_bank_code(account) => {
+            info.insert(INFO_ACCOUNT_ID, account);
+            info.insert(INFO_BANK_ID, bank);
+        }
+        Some((account, bank)) => {
+            info.insert(INFO_ACCOUNT_ID, account);
+            info.insert(INFO_BANK_ID, bank);
+        }
+        None if value.starts_with("CZ") => info.insert_iban(value),
+        None => info.insert(INFO_ACCOUNT_ID, value),
+    }
+}
+
+/// MT940 has two-digit years only; Fio reports cannot be older than 2000, so the century is always 20xx.
+fn parse_yymmdd(s: &str) -> crate::Result<NaiveDate> {
+    let date = s.get(0..6)
+        .and_then(|s| NaiveDate::parse_from_str(&format!("20{}", s), "%Y%m%d").ok())
+        .ok_or_else(|| invalid(&format!("invalid date '{}'", s)))?;
+    Ok(date)
+}
+
+fn parse_balance(value: &str) -> crate::Result<Balance> {
+    let value = value.trim();
+    if !value.is_ascii() || value.len() < 11 {
+        return Err(invalid(&format!("invalid balance '{}'", value)));
+    }
+    let sign = match &value[0..1] {
+        "C" => "",
+        "D" => "-",
+        _ => return Err(invalid(&format!("invalid balance mark '{}'", value))),
+    };
+    Ok(Balance {
+        date: parse_yymmdd(&value[1..7])?,
+        currency: value[7..10].to_string(),
+        amount: format!("{}{}", sign, &value[10..]),
+    })
+}
+
+/// Parses the `:61:` statement line, like `2106010601C1500,00NTRF23771345451//26010123456`.
+fn parse_statement_line(value: &str, currency: String) -> crate::Result<FioTransactionsRecord> {
+    let mut lines = value.lines();
+    let line = lines.next().unwrap_or("").trim();
+    let supplementary = lines.next().unwrap_or("").trim();
+    if !line.is_ascii() || line.len() < 16 {
+        return Err(invalid(&format!("invalid statement line '{}'", line)));
+    }
+    let date = parse_yymmdd(&line[0..6])?;
+    let mut rest = &line[6..];
+    // optional entry date MMDD
+    if rest.len() > 4 && rest[0..4].chars().all(|c| c.is_ascii_digit()) {
+        rest = &rest[4..];
+    }
+    let (negative, mark_len) = if rest.starts_with("RC") {
+        (true, 2)
+    } else if rest.starts_with("RD") {
+        (false, 2)
+    } else if rest.starts_with('C') {
+        (false, 1)
+    } else if rest.starts_with('D') {
+        (true, 1)
+    } else {
+        return Err(invalid(&format!("invalid debit/credit mark in '{}'", line)));
+    };
+    rest = &rest[mark_len..];
+    // optional funds code
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+    let amount_len = rest.find(|c: char| !c.is_ascii_digit() && c != ',').unwrap_or(rest.len());
+    let amount = fio_decimal::parse_fio_decimal(&rest[..amount_len])?;
+    rest = &rest[amount_len..];
+    if rest.len() < 4 {
+        return Err(invalid(&format!("missing transaction type in '{}'", line)));
+    }
+    let tx_type = &rest[..4];
+    let (owner_reference, bank_reference) = match rest[4..].split_once("//") {
+        Some((owner, bank)) => (owner, bank),
+        None => (&rest[4..], ""),
+    };
+    let id_tx = owner_reference.parse()
+        .or_else(|_| bank_reference.parse())
+        .map_err(|_| invalid(&format!("no numeric reference in '{}'", line)))?;
+    let id_command = if owner_reference.parse::<u64>().is_ok() {
+        bank_reference.parse().ok()
+    } else {
+        None
+    };
+    Ok(FioTransactionsRecord {
+        id_tx,
+        date,
+        value: if negative { -amount } else { amount },
+        currency,
+        b_account: String::new(),
+        b_account_name: String::new(),
+        b_bankid: String::new(),
+        b_bank_name: None,
+        ks: String::new(),
+        vs: String::new(),
+        ss: String::new(),
+        custom_id: String::new(),
+        message: String::new(),
+        tx_type: TxType::Other(tx_type.to_string()),
+        who: String::new(),
+        note: supplementary.to_string(),
+        comment: String::new(),
+        bic: String::new(),
+        id_command,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::Amount;
+    use crate::error::Result;
+    use crate::StatementParser;
+
+    use super::Mt940Statement;
+
+    const SAMPLE1: &str = ":20:2345678901-2021-6
+:25:2345678901/2010
+:28C:6/1
+:60F:C210531CZK4789,51
+:61:2106010601C1500,00NTRF23771345451//26010123456
+:86:nájem za červen
+ Novák, Jan
+:61:2106300630D102,93NMSC23794028126
+Platba kartou
+:62F:C210630CZK6186,58
+-
+";
+
+    #[test]
+    fn test_parse_mt940() -> Result<()> {
+        let statement = Mt940Statement::parse(SAMPLE1)?;
+        assert_eq!("2345678901-2021-6", statement.reference);
+        let info = &statement.info;
+        assert_eq!("2345678901", info.account_id()?);
+        assert_eq!("2010", info.bank_id()?);
+        assert_eq!("CZK", info.currency()?);
+        assert_eq!(Amount::from_hundredths(478951), info.opening_balance()?);
+        assert_eq!(Amount::from_hundredths(618658), info.closing_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 5, 31).unwrap(), info.date_start()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), info.date_end()?);
+        assert_eq!("6", info.get_ref()["idList"]);
+
+        assert_eq!(2, statement.transactions.len());
+        let tx = &statement.transactions[0];
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!("CZK", tx.currency);
+        assert_eq!("nájem za červen Novák, Jan", tx.message);
+        assert_eq!(Some(26010123456), tx.id_command);
+        let tx = &statement.transactions[1];
+        assert_eq!(23794028126, tx.id_tx);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), tx.date);
+        assert_eq!(Amount::from_hundredths(-10293), tx.value);
+        assert_eq!("Platba kartou", tx.note);
+        assert_eq!("", tx.message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_iban_account() -> Result<()> {
+        let text = ":20:X\n:25:CZ6220100000002345678901\n:60F:D210531EUR10,00\n:62F:C210630EUR0,00\n-\n";
+        let statement = Mt940Statement::parse(text)?;
+        assert_eq!("2345678901", statement.info.account_id()?);
+        assert_eq!("2010", statement.info.bank_id()?);
+        assert_eq!(Amount::from_hundredths(-1000), statement.info.opening_balance()?);
+        assert!(statement.transactions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_account_order() -> Result<()> {
+        let statement = Mt940Statement::parse(":20:X\n:25:2010/2345678901\n:28C:00000\n-\n")?;
+        assert_eq!("2345678901", statement.info.account_id()?);
+        assert_eq!("2010", statement.info.bank_id()?);
+        assert_eq!("0", statement.info.get_ref()["idList"]);
+        let statement = Mt940Statement::parse(":20:X\n:25:1234/2010\n:28C:012/1\n-\n")?;
+        assert_eq!("1234", statement.info.account_id()?);
+        assert_eq!("2010", statement.info.bank_id()?);
+        assert_eq!("12", statement.info.get_ref()["idList"]);
+        assert!(Mt940Statement::parse(":20:X\n:28C:A1\n-\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let text = ":20:X\n:60F:C210531CZK1,00\n:61:2106X\n";
+        assert!(Mt940Statement::parse(text).is_err());
+        let text = ":20:X\n:60F:Č210531CZK1,00\n-\n";
+        assert!(Mt940Statement::parse(text).is_err());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut bytes = SAMPLE1.as_bytes().to_vec();
+        bytes[8] = 0xff;
+        assert!(matches!(crate::ReportFormat::Mt940.parse_statement(&bytes), Err(crate::FioError::Utf8Error(_))));
+    }
+}
@@ -0,0 +1,410 @@
+//! doc/5.1: OFX format of transactions and reports, see [crate::TxFormat::Ofx] and [crate::ReportFormat::Ofx].
+//!
+//! Both OFX 1.x (SGML, with unclosed leaf elements) and OFX 2.x (XML) are accepted;
+//! the document is read into a simple element tree, where an element either has a text value or children.
+//! Only the first `STMTTRNRS/STMTRS` (bank statement response) is processed.
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::csvdata::{fio_date, fio_decimal, FioTransactionsRecord, TxType};
+use crate::response::*;
+use crate::{Amount, FioError, FioResponseInfo};
+
+/// Parsed OFX statement response.
+#[derive(Debug)]
+pub struct OfxStatement {
+    pub info: FioResponseInfo,
+    /// `LEDGERBAL/BALAMT`
+    pub ledger_balance: Amount,
+    /// `LEDGERBAL/DTASOF`
+    pub ledger_balance_date: Option<NaiveDate>,
+    pub transactions: Vec<FioTransactionsRecord>,
+}
+
+/// Element of the OFX tree.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.name == name)
+    }
+
+    fn path(&self, names: &[&str]) -> Option<&Element> {
+        names.iter().try_fold(self, |e, name| e.child(name))
+    }
+
+    fn text(&self, names: &[&str]) -> Option<&str> {
+        self.path(names)
+            .and_then(|e| e.text.as_deref())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Depth-first search for the first element with given name.
+    fn find(&self, name: &str) -> Option<&Element> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|e| e.find(name))
+    }
+}
+
+impl OfxStatement {
+    /// Decodes the bytes according to the OFX header (`CHARSET:1250` of OFX 1.x, `encoding="windows-1250"`
+    /// of OFX 2.x, otherwise UTF-8) and parses the statement.
+    pub fn parse(bytes: &[u8]) -> crate::Result<Self> {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+        if is_windows_1250(&head) {
+            Self::parse_str(&encoding_rs::WINDOWS_1250.decode_without_bom_handling(bytes).0)
+        } else {
+            Self::parse_str(std::str::from_utf8(bytes)?)
+        }
+    }
+
+    /// Parses already decoded text.
+    pub fn parse_str(text: &str) -> crate::Result<Self> {
+        let root = parse_tree(text)?;
+        let response = root.find("STMTTRNRS")
+            .ok_or_else(|| invalid("missing STMTTRNRS"))?;
+        if let Some(code) = response.text(&["STATUS", "CODE"]) {
+            if code != "0" {
+                let message = response.text(&["STATUS", "MESSAGE"]).unwrap_or("");
+                return Err(invalid(&format!("status code {} {}", code, message)));
+            }
+        }
+        let statement = response.child("STMTRS")
+            .ok_or_else(|| invalid("missing STMTRS"))?;
+
+        let mut info = FioResponseInfo::from(HashMap::new());
+        let currency = statement.text(&["CURDEF"]).unwrap_or("");
+        info.insert(INFO_CURRENCY, currency);
+        if let Some(account) = statement.child("BANKACCTFROM") {
+            match account.text(&["ACCTID"]) {
+                Some(iban) if iban.starts_with("CZ") => info.insert_iban(iban),
+                Some(account_id) => info.insert(INFO_ACCOUNT_ID, account_id),
+                None => {}
+            }
+            if let Some(bank_id) = account.text(&["BANKID"]) {
+                info.insert(INFO_BANK_ID, bank_id);
+            }
+        }
+        let list = statement.child("BANKTRANLIST");
+        if let Some(date) = list.and_then(|l| l.text(&["DTSTART"])) {
+            info.insert(INFO_DATE_START, fio_date::format_fio_date(&parse_date(date)?));
+        }
+        if let Some(date) = list.and_then(|l| l.text(&["DTEND"])) {
+            info.insert(INFO_DATE_END, fio_date::format_fio_date(&parse_date(date)?));
+        }
+        let ledger_balance = statement.text(&["LEDGERBAL", "BALAMT"])
+            .ok_or_else(|| invalid("missing LEDGERBAL"))?;
+        info.insert(INFO_CLOSING_BALANCE, ledger_balance);
+        let ledger_balance = fio_decimal::parse_fio_decimal(ledger_balance)?;
+        let ledger_balance_date = match statement.text(&["LEDGERBAL", "DTASOF"]) {
+            Some(date) => Some(parse_date(date)?),
+            None => None,
+        };
+
+        let transactions = list
+            .map(|l| l.children.iter()
+                .filter(|e| e.name == "STMTTRN")
+                .map(|e| parse_transaction(e, currency))
+                .collect::<crate::Result<Vec<_>>>())
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            info,
+            ledger_balance,
+            ledger_balance_date,
+            transactions,
+        })
+    }
+}
+
+fn invalid(message: &str) -> FioError {
+    FioError::InvalidData(format!("OFX: {}", message))
+}
+
+/// OFX dates look like `20210630`, `20210630120000` or `20210630120000.000[+2:CEST]`.
+fn parse_date(s: &str) -> crate::Result<NaiveDate> {
+    s.get(0..8)
+        .and_then(|s| NaiveDate::parse_from_str(s, "%Y%m%d").ok())
+        .ok_or_else(|| invalid(&format!("invalid date '{}'", s)))
+}
+
+fn parse_transaction(tx: &Element, currency: &str) -> crate::Result<FioTransactionsRecord> {
+    let text = |names: &[&str]| tx.text(names).unwrap_or("").to_string();
+    let id_tx = tx.text(&["FITID"]).ok_or_else(|| invalid("missing FITID"))?;
+    let date = tx.text(&["DTPOSTED"]).ok_or_else(|| invalid("missing DTPOSTED"))?;
+    let amount = tx.text(&["TRNAMT"]).ok_or_else(|| invalid("missing TRNAMT"))?;
+    let currency = tx.text(&["CURRENCY", "CURSYM"])
+        .or_else(|| tx.text(&["ORIGCURRENCY", "CURSYM"]))
+        .unwrap_or(currency);
+    Ok(FioTransactionsRecord {
+        id_tx: id_tx.parse()?,
+        date: parse_date(date)?,
+        value: fio_decimal::parse_fio_decimal(amount)?,
+        currency: currency.to_string(),
+        b_account: text(&["BANKACCTTO", "ACCTID"]),
+        b_account_name: text(&["NAME"]),
+        b_bankid: text(&["BANKACCTTO", "BANKID"]),
+        b_bank_name: None,
+        ks: String::new(),
+        vs: String::new(),
+        ss: String::new(),
+        custom_id: String::new(),
+        message: text(&["MEMO"]),
+        tx_type: TxType::parse(text(&["TRNTYPE"])),
+        who: String::new(),
+        note: String::new(),
+        comment: String::new(),
+        bic: String::new(),
+        id_command: None,
+    })
+}
+
+/// Builds the element tree from SGML or XML markup, ignoring the header and processing instructions.
+/// Tells if the SGML header or the XML declaration announces Windows-1250.
+fn is_windows_1250(head: &str) -> bool {
+    if head.contains("CHARSET:1250") {
+        return true;
+    }
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    match (head.starts_with("<?xml"), head.find("?>")) {
+        (true, Some(end)) => {
+            let declaration = head[..end].to_ascii_lowercase().replace('\'', "\"");
+            declaration.contains("encoding=\"windows-1250\"")
+        }
+        _ => false,
+    }
+}
+
+fn parse_tree(text: &str) -> crate::Result<Element> {
+    let start = text.find("<OFX>").ok_or_else(|| invalid("missing <OFX>"))?;
+    let mut stack = vec![Element::default()];
+    let mut rest = &text[start..];
+    while let Some(open) = rest.find('<') {
+        let close = rest[open..].find('>')
+            .map(|n| open + n)
+            .ok_or_else(|| invalid("unterminated tag"))?;
+        let tag = rest[open + 1..close].trim();
+        rest = &rest[close + 1..];
+        let value_end = rest.find('<').unwrap_or(rest.len());
+        let value = unescape(rest[..value_end].trim());
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            // close the element, including any unclosed SGML elements within
+            if let Some(depth) = stack.iter().rposition(|e| e.name == name) {
+                while stack.len() > depth {
+                    let element = stack.pop().unwrap_or_default();
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(element);
+                    }
+                }
+            }
+        } else if let Some(name) = tag.strip_suffix('/') {
+            let element = Element { name: name.trim().to_string(), text: Some(String::new()), children: vec![] };
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(element);
+            }
+        } else if value.is_empty() {
+            stack.push(Element { name: tag.to_string(), ..Default::default() });
+        } else {
+            // leaf element: SGML does not close it, XML closes it immediately
+            rest = &rest[value_end..];
+            let closing = format!("</{}>", tag);
+            if rest.starts_with(&closing) {
+                rest = &rest[closing.len()..];
+            }
+            let element = Element { name: tag.to_string(), text: Some(value), children: vec![] };
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(element);
+            }
+        }
+    }
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap_or_default();
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(element);
+        }
+    }
+    stack.pop()
+        .and_then(|root| root.children.into_iter().next())
+        .ok_or_else(|| invalid("empty document"))
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::Amount;
+    use crate::error::Result;
+
+    use super::OfxStatement;
+
+    const SAMPLE_SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+SECURITY:NONE
+ENCODING:UTF-8
+CHARSET:NONE
+COMPRESSION:NONE
+OLDFILEUID:NONE
+NEWFILEUID:NONE
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>20210701<LANGUAGE>CES</SONRS></SIGNONMSGSRSV1>
+<BANKMSGSRSV1>
+<STMTTRNRS>
+<TRNUID>1
+<STATUS><CODE>0<SEVERITY>INFO</STATUS>
+<STMTRS>
+<CURDEF>CZK
+<BANKACCTFROM><BANKID>2010<ACCTID>2345678901<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST>
+<DTSTART>20210601
+<DTEND>20210630
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20210601
+<TRNAMT>1500.00
+<FITID>23771345451
+<NAME>Novák, Jan
+<BANKACCTTO><BANKID>2010<ACCTID>2702016516<ACCTTYPE>CHECKING</BANKACCTTO>
+<MEMO>nájem &amp; služby
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20210630120000.000[+2:CEST]
+<TRNAMT>-102.93
+<FITID>23794028126
+<NAME>Shop
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>6186.58<DTASOF>20210630</LEDGERBAL>
+</STMTRS>
+</STMTTRNRS>
+</BANKMSGSRSV1>
+</OFX>
+";
+
+    const SAMPLE_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?OFX OFXHEADER="200" VERSION="211" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>
+<OFX>
+  <BANKMSGSRSV1>
+    <STMTTRNRS>
+      <TRNUID>1</TRNUID>
+      <STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>
+      <STMTRS>
+        <CURDEF>CZK</CURDEF>
+        <BANKACCTFROM><BANKID>2010</BANKID><ACCTID>2345678901</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>
+        <BANKTRANLIST>
+          <DTSTART>20210601</DTSTART>
+          <DTEND>20210630</DTEND>
+          <STMTTRN>
+            <TRNTYPE>CREDIT</TRNTYPE>
+            <DTPOSTED>20210601</DTPOSTED>
+            <TRNAMT>1500.00</TRNAMT>
+            <FITID>23771345451</FITID>
+            <NAME>Novák, Jan</NAME>
+            <BANKACCTTO><BANKID>2010</BANKID><ACCTID>2702016516</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTTO>
+            <MEMO>nájem &amp; služby</MEMO>
+          </STMTTRN>
+          <STMTTRN>
+            <TRNTYPE>DEBIT</TRNTYPE>
+            <DTPOSTED>20210630120000.000[+2:CEST]</DTPOSTED>
+            <TRNAMT>-102.93</TRNAMT>
+            <FITID>23794028126</FITID>
+            <NAME>Shop</NAME>
+          </STMTTRN>
+        </BANKTRANLIST>
+        <LEDGERBAL><BALAMT>6186.58</BALAMT><DTASOF>20210630</DTASOF></LEDGERBAL>
+      </STMTRS>
+    </STMTTRNRS>
+  </BANKMSGSRSV1>
+</OFX>
+"#;
+
+    fn check(statement: OfxStatement) -> Result<()> {
+        assert_eq!(Amount::from_hundredths(618658), statement.ledger_balance);
+        assert_eq!(Some(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap()), statement.ledger_balance_date);
+        let info = &statement.info;
+        assert_eq!("2345678901", info.account_id()?);
+        assert_eq!("2010", info.bank_id()?);
+        assert_eq!("CZK", info.currency()?);
+        assert_eq!(Amount::from_hundredths(618658), info.closing_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(), info.date_start()?);
+
+        assert_eq!(2, statement.transactions.len());
+        let tx = &statement.transactions[0];
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!("CZK", tx.currency);
+        assert_eq!("Novák, Jan", tx.b_account_name);
+        assert_eq!("2702016516", tx.b_account);
+        assert_eq!("2010", tx.b_bankid);
+        assert_eq!("nájem & služby", tx.message);
+        let tx = &statement.transactions[1];
+        assert_eq!(Amount::from_hundredths(-10293), tx.value);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), tx.date);
+        assert_eq!("", tx.b_account);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_sgml() -> Result<()> {
+        check(OfxStatement::parse(SAMPLE_SGML.as_bytes())?)
+    }
+
+    #[test]
+    fn test_parse_xml() -> Result<()> {
+        check(OfxStatement::parse(SAMPLE_XML.as_bytes())?)
+    }
+
+    #[test]
+    fn test_parse_windows_1250() -> Result<()> {
+        let text = SAMPLE_SGML.replace("CHARSET:NONE", "CHARSET:1250");
+        let (bytes, _, _) = encoding_rs::WINDOWS_1250.encode(&text);
+        let statement = OfxStatement::parse(&bytes)?;
+        assert_eq!("Novák, Jan", statement.transactions[0].b_account_name);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_xml_windows_1250() -> Result<()> {
+        let text = SAMPLE_XML.replace(r#"encoding="UTF-8""#, r#"encoding="windows-1250""#);
+        let (bytes, _, _) = encoding_rs::WINDOWS_1250.encode(&text);
+        let statement = OfxStatement::parse(&bytes)?;
+        assert_eq!("Novák, Jan", statement.transactions[0].b_account_name);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut bytes = SAMPLE_XML.as_bytes().to_vec();
+        let index = SAMPLE_XML.find("<OFX>").unwrap() + 1;
+        bytes[index] = 0xff;
+        assert!(matches!(OfxStatement::parse(&bytes), Err(crate::FioError::Utf8Error(_))));
+    }
+
+    #[test]
+    fn test_error_status() {
+        let text = SAMPLE_XML.replace("<STATUS><CODE>0</CODE>", "<STATUS><CODE>2000</CODE>");
+        assert!(OfxStatement::parse(text.as_bytes()).is_err());
+    }
+}
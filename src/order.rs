@@ -0,0 +1,614 @@
+//! doc/6.3: Payment orders with the mandatory fields required by their constructors.
+//!
+//! Unlike [DomesticPayment], [T2Payment] and [ForeignPayment], an order cannot be created without
+//! the fields the bank requires; the XML is generated by converting the order to the matching builder.
+//! Orders can be read back from a validated [Payment], e.g. one parsed by [Payment::parse_batch].
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::import::ToPaymentXml;
+use crate::{AbroadTransaction, Amount, Bic, CzAccount, DetailsOfCharges, DomesticPayment, DomesticSymbolsBuilder, DomesticTransaction, FioError, ForeignPayment, ForeignTransaction, Iban, Payment, PaymentBuilder, PaymentType, Result, T2Payment, T2Transaction};
+
+/// Symbols of domestic payments, all optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Symbols {
+    ks: Option<String>,
+    vs: Option<String>,
+    ss: Option<String>,
+}
+
+/// Beneficiary of foreign payment; all fields are mandatory in doc/6.3.3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beneficiary {
+    /// (35i) název příjemce
+    pub name: String,
+    /// (35i) ulice příjemce
+    pub street: String,
+    /// (35i) město příjemce
+    pub city: String,
+    /// (3x) stát příjemce
+    pub country: String,
+}
+
+/// 6.3.1 XML příkaz platba v rámci ČR
+///
+/// ```
+/// # use fio_api::{Amount, DomesticOrder};
+/// let order = DomesticOrder::new("2345678901", "CZK", Amount::from_hundredths(10293), "19-2000145399/0800".parse()?)
+///     .vs("2021006")
+///     .message_for_recipient("faktura 2021006");
+/// # Ok::<(), fio_api::FioError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomesticOrder {
+    account_from: String,
+    currency: String,
+    amount: Amount,
+    account_to: CzAccount,
+    date: NaiveDate,
+    symbols: Symbols,
+    message_for_recipient: Option<String>,
+    comment: Option<String>,
+    payment_reason: Option<u16>,
+    payment_type: Option<PaymentType>,
+}
+
+impl DomesticOrder {
+    /// Order with mandatory fields, dated today.
+    pub fn new(account_from: &str, currency: &str, amount: Amount, account_to: CzAccount) -> Self {
+        Self {
+            account_from: account_from.to_string(),
+            currency: currency.to_string(),
+            amount,
+            account_to,
+            date: today(),
+            symbols: Symbols::default(),
+            message_for_recipient: None,
+            comment: None,
+            payment_reason: None,
+            payment_type: None,
+        }
+    }
+
+    /// (RRRR-MM-DD) datum
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// (4n) konstantní symbol
+    pub fn ks(mut self, ks: &str) -> Self {
+        self.symbols.ks = Some(ks.to_string());
+        self
+    }
+
+    /// (10n) variabilní symbol
+    pub fn vs(mut self, vs: &str) -> Self {
+        self.symbols.vs = Some(vs.to_string());
+        self
+    }
+
+    /// (10n) specifický symbol
+    pub fn ss(mut self, ss: &str) -> Self {
+        self.symbols.ss = Some(ss.to_string());
+        self
+    }
+
+    /// (140i) zpráva pro příjemce
+    pub fn message_for_recipient(mut self, message: &str) -> Self {
+        self.message_for_recipient = Some(message.to_string());
+        self
+    }
+
+    /// (255i) Vaše označení
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// (3!n) platební titul – viz 6.3.4 Platební titul
+    pub fn payment_reason(mut self, payment_reason: u16) -> Self {
+        self.payment_reason = Some(payment_reason);
+        self
+    }
+
+    /// (6!n) typ platby
+    pub fn payment_type(mut self, payment_type: PaymentType) -> Self {
+        self.payment_type = Some(payment_type);
+        self
+    }
+}
+
+impl From<DomesticOrder> for Payment {
+    fn from(order: DomesticOrder) -> Self {
+        let mut payment = DomesticPayment::new(&order.account_from, &order.currency)
+            .amount(order.amount)
+            .cz_account_to(&order.account_to)
+            .date(order.date);
+        payment = with_symbols(payment, order.symbols);
+        if let Some(message) = order.message_for_recipient {
+            payment = payment.message_for_recipient(message);
+        }
+        if let Some(payment_type) = order.payment_type {
+            payment = DomesticTransaction::payment_type(payment, payment_type);
+        }
+        with_common(payment, order.comment, order.payment_reason).into()
+    }
+}
+
+impl TryFrom<Payment> for DomesticOrder {
+    type Error = FioError;
+
+    /// Fails with [FioError::InvalidData] when the payment is not a `DomesticTransaction`, with [FioError::InvalidPayment]
+    /// when its fields violate doc/6.3, and with [FioError::InvalidData] when `paymentType` is not a known [PaymentType].
+    fn try_from(payment: Payment) -> Result<Self> {
+        check_type(&payment, "DomesticTransaction")?;
+        let field = |name| payment.get(name).unwrap_or_default();
+        let account_to = format!("{}/{}", field("accountTo"), field("bankCode")).parse()?;
+        let mut order = Self::new(field("accountFrom"), field("currency"), field("amount").parse()?, account_to)
+            .date(field("date").parse()?);
+        order.symbols = symbols(&payment);
+        order.message_for_recipient = optional(&payment, "messageForRecipient");
+        order.comment = optional(&payment, "comment");
+        order.payment_reason = payment_reason(&payment)?;
+        order.payment_type = optional(&payment, "paymentType")
+            .map(|value| parse_code::<PaymentType>("paymentType", &value))
+            .transpose()?;
+        Ok(order)
+    }
+}
+
+/// 6.3.2 XML příkaz Europlatba
+#[derive(Debug, Clone, PartialEq)]
+pub struct T2Order {
+    account_from: String,
+    currency: String,
+    amount: Amount,
+    account_to: Iban,
+    bic: Bic,
+    benef_name: String,
+    date: NaiveDate,
+    symbols: Symbols,
+    benef_street: Option<String>,
+    benef_city: Option<String>,
+    benef_country: Option<String>,
+    remittance_info: [Option<String>; 3],
+    comment: Option<String>,
+    payment_reason: Option<u16>,
+    priority: Option<bool>,
+}
+
+impl T2Order {
+    /// Order with mandatory fields, dated today.
+    pub fn new(account_from: &str, currency: &str, amount: Amount, account_to: Iban, bic: Bic, benef_name: &str) -> Self {
+        Self {
+            account_from: account_from.to_string(),
+            currency: currency.to_string(),
+            amount,
+            account_to,
+            bic,
+            benef_name: benef_name.to_string(),
+            date: today(),
+            symbols: Symbols::default(),
+            benef_street: None,
+            benef_city: None,
+            benef_country: None,
+            remittance_info: Default::default(),
+            comment: None,
+            payment_reason: None,
+            priority: None,
+        }
+    }
+
+    /// (RRRR-MM-DD) datum
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// (4n) konstantní symbol
+    pub fn ks(mut self, ks: &str) -> Self {
+        self.symbols.ks = Some(ks.to_string());
+        self
+    }
+
+    /// (10n) variabilní symbol
+    pub fn vs(mut self, vs: &str) -> Self {
+        self.symbols.vs = Some(vs.to_string());
+        self
+    }
+
+    /// (10n) specifický symbol
+    pub fn ss(mut self, ss: &str) -> Self {
+        self.symbols.ss = Some(ss.to_string());
+        self
+    }
+
+    /// (35i, 35i, 3x) adresa příjemce
+    pub fn benef_address(mut self, street: &str, city: &str, country: &str) -> Self {
+        self.benef_street = Some(street.to_string());
+        self.benef_city = Some(city.to_string());
+        self.benef_country = Some(country.to_string());
+        self
+    }
+
+    /// (35i) zpráva pro příjemce, `line` 1 to 3; other lines are ignored
+    pub fn remittance_info(mut self, line: usize, text: &str) -> Self {
+        if let Some(info) = line.checked_sub(1).and_then(|index| self.remittance_info.get_mut(index)) {
+            *info = Some(text.to_string());
+        }
+        self
+    }
+
+    /// (255i) Vaše označení
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// (3!n) platební titul – viz 6.3.4 Platební titul
+    pub fn payment_reason(mut self, payment_reason: u16) -> Self {
+        self.payment_reason = Some(payment_reason);
+        self
+    }
+
+    /// (6!n) typ platby: prioritní, or standardní
+    pub fn priority(mut self, priority: bool) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+}
+
+impl From<T2Order> for Payment {
+    fn from(order: T2Order) -> Self {
+        let mut payment = T2Payment::new(&order.account_from, &order.currency)
+            .amount(order.amount)
+            .account_to(&order.account_to)
+            .bic(&order.bic)
+            .benef_name(order.benef_name)
+            .date(order.date);
+        payment = with_symbols(payment, order.symbols);
+        if let Some(street) = order.benef_street {
+            payment = payment.benef_street(street);
+        }
+        if let Some(city) = order.benef_city {
+            payment = payment.benef_city(city);
+        }
+        if let Some(country) = order.benef_country {
+            payment = payment.benef_country(country);
+        }
+        let [info_1, info_2, info_3] = order.remittance_info;
+        if let Some(info) = info_1 {
+            payment = payment.remittance_info_1(info);
+        }
+        if let Some(info) = info_2 {
+            payment = payment.remittance_info_2(info);
+        }
+        if let Some(info) = info_3 {
+            payment = payment.remittance_info_3(info);
+        }
+        if let Some(priority) = order.priority {
+            payment = payment.payment_type(priority);
+        }
+        with_common(payment, order.comment, order.payment_reason).into()
+    }
+}
+
+impl TryFrom<Payment> for T2Order {
+    type Error = FioError;
+
+    /// Fails with [FioError::InvalidData] when the payment is not a `T2Transaction`, with [FioError::InvalidPayment]
+    /// when its fields violate doc/6.3, and with [FioError::InvalidData] when `paymentType` is neither standard nor priority.
+    fn try_from(payment: Payment) -> Result<Self> {
+        check_type(&payment, "T2Transaction")?;
+        let field = |name| payment.get(name).unwrap_or_default();
+        let mut order = Self::new(field("accountFrom"), field("currency"), field("amount").parse()?,
+                                  field("accountTo").parse()?, field("bic").parse()?, field("benefName"))
+            .date(field("date").parse()?);
+        order.symbols = symbols(&payment);
+        order.benef_street = optional(&payment, "benefStreet");
+        order.benef_city = optional(&payment, "benefCity");
+        order.benef_country = optional(&payment, "benefCountry");
+        order.remittance_info = [
+            optional(&payment, "remittanceInfo1"),
+            optional(&payment, "remittanceInfo2"),
+            optional(&payment, "remittanceInfo3"),
+        ];
+        order.comment = optional(&payment, "comment");
+        order.payment_reason = payment_reason(&payment)?;
+        order.priority = match payment.get("paymentType") {
+            None | Some("") => None,
+            Some("431008") => Some(false),
+            Some("431009") => Some(true),
+            Some(value) => return Err(FioError::InvalidData(format!("Invalid paymentType '{}'", value))),
+        };
+        Ok(order)
+    }
+}
+
+/// 6.3.3 XML příkaz zahraniční platba
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignOrder {
+    account_from: String,
+    currency: String,
+    amount: Amount,
+    account_to: Iban,
+    bic: Bic,
+    beneficiary: Beneficiary,
+    remittance_info_1: String,
+    details_of_charges: DetailsOfCharges,
+    date: NaiveDate,
+    remittance_info: [Option<String>; 3],
+    comment: Option<String>,
+    payment_reason: Option<u16>,
+}
+
+impl ForeignOrder {
+    /// Order with mandatory fields, dated today.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(account_from: &str, currency: &str, amount: Amount, account_to: Iban, bic: Bic,
+               beneficiary: Beneficiary, remittance_info_1: &str, details_of_charges: DetailsOfCharges) -> Self {
+        Self {
+            account_from: account_from.to_string(),
+            currency: currency.to_string(),
+            amount,
+            account_to,
+            bic,
+            beneficiary,
+            remittance_info_1: remittance_info_1.to_string(),
+            details_of_charges,
+            date: today(),
+            remittance_info: Default::default(),
+            comment: None,
+            payment_reason: None,
+        }
+    }
+
+    /// (RRRR-MM-DD) datum
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// (35i) zpráva pro příjemce, `line` 2 to 4; other lines are ignored
+    pub fn remittance_info(mut self, line: usize, text: &str) -> Self {
+        if let Some(info) = line.checked_sub(2).and_then(|index| self.remittance_info.get_mut(index)) {
+            *info = Some(text.to_string());
+        }
+        self
+    }
+
+    /// (255i) Vaše označení
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    /// (3!n) platební titul – viz 6.3.4 Platební titul
+    pub fn payment_reason(mut self, payment_reason: u16) -> Self {
+        self.payment_reason = Some(payment_reason);
+        self
+    }
+}
+
+impl From<ForeignOrder> for Payment {
+    fn from(order: ForeignOrder) -> Self {
+        let beneficiary = order.beneficiary;
+        let mut payment = ForeignPayment::new(&order.account_from, &order.currency)
+            .amount(order.amount)
+            .account_to(&order.account_to)
+            .bic(&order.bic)
+            .benef_name(beneficiary.name)
+            .benef_street(beneficiary.street)
+            .benef_city(beneficiary.city)
+            .benef_country(beneficiary.country)
+            .remittance_info_1(order.remittance_info_1)
+            .details_of_charges(order.details_of_charges)
+            .date(order.date);
+        let [info_2, info_3, info_4] = order.remittance_info;
+        if let Some(info) = info_2 {
+            payment = payment.remittance_info_2(info);
+        }
+        if let Some(info) = info_3 {
+            payment = payment.remittance_info_3(info);
+        }
+        if let Some(info) = info_4 {
+            payment = payment.remittance_info_4(info);
+        }
+        with_common(payment, order.comment, order.payment_reason).into()
+    }
+}
+
+impl TryFrom<Payment> for ForeignOrder {
+    type Error = FioError;
+
+    /// Fails with [FioError::InvalidData] when the payment is not a `ForeignTransaction`, with [FioError::InvalidPayment]
+    /// when its fields violate doc/6.3, and with [FioError::InvalidData] when `detailsOfCharges` is not a known [DetailsOfCharges].
+    fn try_from(payment: Payment) -> Result<Self> {
+        check_type(&payment, "ForeignTransaction")?;
+        let field = |name| payment.get(name).unwrap_or_default();
+        let beneficiary = Beneficiary {
+            name: field("benefName").to_string(),
+            street: field("benefStreet").to_string(),
+            city: field("benefCity").to_string(),
+            country: field("benefCountry").to_string(),
+        };
+        let mut order = Self::new(field("accountFrom"), field("currency"), field("amount").parse()?,
+                                  field("accountTo").parse()?, field("bic").parse()?, beneficiary,
+                                  field("remittanceInfo1"),
+                                  parse_code("detailsOfCharges", field("detailsOfCharges"))?)
+            .date(field("date").parse()?);
+        order.remittance_info = [
+            optional(&payment, "remittanceInfo2"),
+            optional(&payment, "remittanceInfo3"),
+            optional(&payment, "remittanceInfo4"),
+        ];
+        order.comment = optional(&payment, "comment");
+        order.payment_reason = payment_reason(&payment)?;
+        Ok(order)
+    }
+}
+
+/// Checks the transaction type and validates the fields, so that the mandatory ones can be read as present.
+fn check_type(payment: &Payment, transaction_type: &str) -> Result<()> {
+    match payment.transaction_type() {
+        Some(actual) if actual == transaction_type => payment.validate(),
+        actual => Err(FioError::InvalidData(format!("Expected {}, got {}", transaction_type, actual.unwrap_or("no transaction type")))),
+    }
+}
+
+fn optional(payment: &Payment, field: &str) -> Option<String> {
+    payment.get(field)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn symbols(payment: &Payment) -> Symbols {
+    Symbols {
+        ks: optional(payment, "ks"),
+        vs: optional(payment, "vs"),
+        ss: optional(payment, "ss"),
+    }
+}
+
+fn payment_reason(payment: &Payment) -> Result<Option<u16>> {
+    Ok(optional(payment, "paymentReason")
+        .map(|value| value.parse())
+        .transpose()?)
+}
+
+fn parse_code<T: FromStr>(field: &str, value: &str) -> Result<T> {
+    T::from_str(value)
+        .map_err(|_| FioError::InvalidData(format!("Invalid {} '{}'", field, value)))
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+fn with_symbols<P: DomesticSymbolsBuilder>(mut payment: P, symbols: Symbols) -> P {
+    if let Some(ks) = symbols.ks {
+        payment = payment.ks(ks);
+    }
+    if let Some(vs) = symbols.vs {
+        payment = payment.vs(vs);
+    }
+    if let Some(ss) = symbols.ss {
+        payment = payment.ss(ss);
+    }
+    payment
+}
+
+fn with_common<P: PaymentBuilder>(mut payment: P, comment: Option<String>, payment_reason: Option<u16>) -> P {
+    if let Some(comment) = comment {
+        payment = payment.comment(comment);
+    }
+    if let Some(payment_reason) = payment_reason {
+        payment = payment.payment_reason(payment_reason);
+    }
+    payment
+}
+
+macro_rules! order_to_payment_xml {
+    ($($order:ty),*) => {$(
+        impl ToPaymentXml for $order {
+            fn to_payment_xml(&self) -> crate::Result<String> {
+                Payment::from(self.clone()).to_payment_xml()
+            }
+        }
+    )*};
+}
+
+order_to_payment_xml!(DomesticOrder, T2Order, ForeignOrder);
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use chrono::NaiveDate;
+
+    use crate::import::ToPaymentXml;
+    use crate::{Amount, DetailsOfCharges, DomesticPayment, DomesticSymbolsBuilder, DomesticTransaction, FioError, Payment, PaymentBuilder, PaymentType};
+
+    use super::{Beneficiary, DomesticOrder, ForeignOrder, T2Order};
+
+    #[test]
+    fn test_domestic_same_xml() -> crate::Result<()> {
+        let date = NaiveDate::from_ymd_opt(2021, 7, 1).unwrap();
+        let order = DomesticOrder::new("2345678901", "CZK", Amount::from_hundredths(10293), "19-2000145399/0800".parse()?)
+            .date(date)
+            .vs("2021006")
+            .message_for_recipient("faktura");
+        let payment = DomesticPayment::new("2345678901", "CZK")
+            .amount(Amount::from_hundredths(10293))
+            .account_to("19-2000145399", "0800")
+            .date(date)
+            .vs("2021006")
+            .message_for_recipient("faktura");
+        assert_eq!(payment.to_payment_xml()?, order.to_payment_xml()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_abroad_orders_are_valid() -> crate::Result<()> {
+        let t2 = T2Order::new("2345678901", "EUR", Amount::from_hundredths(3410),
+                              "DE89370400440532013000".parse()?, "COBADEFF".parse()?, "Max Mustermann")
+            .remittance_info(1, "invoice 1");
+        crate::Payment::from(t2.clone()).validate()?;
+        assert!(t2.to_payment_xml()?.contains("<T2Transaction>"));
+
+        let beneficiary = Beneficiary {
+            name: "John Smith".to_string(),
+            street: "1 High Street".to_string(),
+            city: "London".to_string(),
+            country: "GB".to_string(),
+        };
+        let foreign = ForeignOrder::new("2345678901", "USD", Amount::from_hundredths(4310),
+                                        "GB82WEST12345698765432".parse()?, "NWBKGB2L".parse()?,
+                                        beneficiary, "invoice 2", DetailsOfCharges::SHA)
+            .remittance_info(4, "ref");
+        crate::Payment::from(foreign.clone()).validate()?;
+        let xml = foreign.to_payment_xml()?;
+        assert!(xml.contains("<benefCity>London</benefCity>"), "{}", xml);
+        assert!(xml.contains("<remittanceInfo4>ref</remittanceInfo4>"), "{}", xml);
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_from_payment() -> crate::Result<()> {
+        let date = NaiveDate::from_ymd_opt(2021, 7, 1).unwrap();
+        let domestic = DomesticOrder::new("2345678901", "CZK", Amount::from_hundredths(10293), "19-2000145399/0800".parse()?)
+            .date(date)
+            .ss("42")
+            .payment_type(PaymentType::Priority);
+        assert_eq!(domestic, DomesticOrder::try_from(Payment::from(domestic.clone()))?);
+
+        let t2 = T2Order::new("2345678901", "EUR", Amount::from_hundredths(3410),
+                              "DE89370400440532013000".parse()?, "COBADEFF".parse()?, "Max Mustermann")
+            .date(date)
+            .remittance_info(2, "invoice 1")
+            .priority(true);
+        assert_eq!(t2, T2Order::try_from(Payment::from(t2.clone()))?);
+
+        let beneficiary = Beneficiary {
+            name: "John Smith".to_string(),
+            street: "1 High Street".to_string(),
+            city: "London".to_string(),
+            country: "GB".to_string(),
+        };
+        let foreign = ForeignOrder::new("2345678901", "USD", Amount::from_hundredths(4310),
+                                        "GB82WEST12345698765432".parse()?, "NWBKGB2L".parse()?,
+                                        beneficiary, "invoice 2", DetailsOfCharges::OUR)
+            .date(date)
+            .payment_reason(110);
+        let parsed = Payment::parse_batch(&foreign.to_payment_xml()?)?;
+        assert_eq!(foreign, ForeignOrder::try_from(parsed[0].clone())?);
+
+        assert!(matches!(T2Order::try_from(Payment::from(domestic)), Err(FioError::InvalidData(_))));
+        let invalid = Payment::from(DomesticPayment::new("2345678901", "CZK").account_to("1234567890", "0800"));
+        assert!(matches!(DomesticOrder::try_from(invalid), Err(FioError::InvalidPayment(_))));
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt::{Display, Formatter};
 use std::io::{Error, ErrorKind, Result};
 
 use chrono::Datelike;
@@ -59,9 +60,9 @@ impl FioPeriod {
     }
 }
 
-impl ToString for FioPeriod {
-    fn to_string(&self) -> String {
-        format!("{},{}", self.year, self.month)
+impl Display for FioPeriod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.year, self.month)
     }
 }
 
@@ -0,0 +1,125 @@
+//! Multiple tokens of the same account, to minimize waiting for the next API call.
+//!
+//! Each token has its own 30-second window (doc/8.3); the pool sends each request with the token
+//! whose window opens first. Read-only tokens and tokens allowed to import payments are kept
+//! in separate groups, so that downloads never consume the window of an import token.
+use std::sync::Mutex;
+
+use reqwest::Response;
+use tokio::time::Instant;
+
+use crate::import::ToPaymentXml;
+use crate::{FioClient, FioClientWithImport, FioError, FioExportReq, FioResponse, ImportResult, Statement};
+
+#[derive(Default)]
+pub struct FioTokenPool {
+    readers: Vec<FioClient>,
+    importers: Vec<FioClientWithImport>,
+    /// makes choosing a token and reserving its slot one step
+    dispatch: Mutex<()>,
+}
+
+impl FioTokenPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a client with read-only token, used for [Self::export] and [Self::export_statement].
+    pub fn with_reader(mut self, client: FioClient) -> Self {
+        self.readers.push(client);
+        self
+    }
+
+    /// Adds a client with token allowed to import payments, used for [Self::import].
+    pub fn with_importer(mut self, client: FioClientWithImport) -> Self {
+        self.importers.push(client);
+        self
+    }
+
+    /// Like [FioClient::export], using the reader token that is available first.
+    pub async fn export(&self, fio_req: FioExportReq) -> crate::Result<Response> {
+        let (client, slot) = self.reserve_reader()?;
+        client.execute_export(&fio_req, slot).await
+    }
+
+    /// Like [FioClient::export_statement], using the reader token that is available first.
+    pub async fn export_statement(&self, fio_req: FioExportReq) -> crate::Result<Statement> {
+        let parser = fio_req.statement_parser()
+            .ok_or_else(|| FioError::UnsupportedFormat(Into::<&str>::into(&fio_req).to_string()))?;
+        let (client, slot) = self.reserve_reader()?;
+        let response = client.execute_export(&fio_req, slot).await?;
+        FioResponse::try_from(response).await?
+            .statement(parser)
+    }
+
+    /// Like [FioClientWithImport::import], using the import token that is available first.
+    pub async fn import<P: ToPaymentXml>(&self, payment: P) -> crate::Result<ImportResult> {
+        let payment_xml = payment.to_payment_xml()?;
+        let (importer, slot) = self.reserve_importer()?;
+        importer.execute_import(&payment_xml, slot).await
+    }
+
+    fn reserve_reader(&self) -> crate::Result<(&FioClient, Instant)> {
+        let _dispatch = self.dispatch.lock().unwrap();
+        let client = self.readers.iter()
+            .min_by_key(|client| client.limiter().next_slot())
+            .ok_or_else(|| FioError::NoToken("export".to_string()))?;
+        Ok((client, client.reserve_slot()?))
+    }
+
+    fn reserve_importer(&self) -> crate::Result<(&FioClientWithImport, Instant)> {
+        let _dispatch = self.dispatch.lock().unwrap();
+        let importer = self.importers.iter()
+            .min_by_key(|importer| importer.fio.limiter().next_slot())
+            .ok_or_else(|| FioError::NoToken("import".to_string()))?;
+        Ok((importer, importer.fio.reserve_slot()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::Instant;
+
+    use crate::{FioClient, FioClientWithImport, FioError, FioExportReq, TxFormat};
+
+    use super::FioTokenPool;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reserve_spreads_over_tokens() -> crate::Result<()> {
+        let pool = FioTokenPool::new()
+            .with_reader(FioClient::new("A"))
+            .with_reader(FioClient::new("B"));
+        let start = Instant::now();
+        let mut reservations = vec![];
+        for _ in 0..4 {
+            let (client, slot) = pool.reserve_reader()?;
+            reservations.push((client.token.clone(), slot.duration_since(start).as_secs_f64().round() as u64));
+        }
+        let expected = [("A", 0), ("B", 0), ("A", 30), ("B", 30)];
+        assert_eq!(expected.iter().map(|(t, s)| (t.to_string(), *s)).collect::<Vec<_>>(), reservations);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reserve_spreads_over_importers() -> crate::Result<()> {
+        let pool = FioTokenPool::new()
+            .with_importer(FioClientWithImport::new(FioClient::new("A"), "2345678901", "CZK"))
+            .with_importer(FioClientWithImport::new(FioClient::new("B"), "2345678901", "CZK"));
+        let start = Instant::now();
+        let mut reservations = vec![];
+        for _ in 0..4 {
+            let (importer, slot) = pool.reserve_importer()?;
+            reservations.push((importer.fio().token.clone(), slot.duration_since(start).as_secs_f64().round() as u64));
+        }
+        let expected = [("A", 0), ("B", 0), ("A", 30), ("B", 30)];
+        assert_eq!(expected.iter().map(|(t, s)| (t.to_string(), *s)).collect::<Vec<_>>(), reservations);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_no_token() {
+        let pool = FioTokenPool::new();
+        let result = pool.export(FioExportReq::Last { format: TxFormat::Csv }).await;
+        assert!(matches!(result, Err(FioError::NoToken(_))));
+    }
+}
@@ -0,0 +1,320 @@
+//! doc/8.3: Minimal interval between two requests with the same token.
+//!
+//! The limiter hands out time slots; each caller reserves the next free slot before waiting for it,
+//! so that concurrent requests through one client are spread over consecutive slots instead of all
+//! waking up at the same time and fighting for the first one.
+//!
+//! The slots are recorded in a [RateLimitStore]; by default, each client has its own in-memory store,
+//! but a shared store lets multiple clients - even in separate processes - cooperate on one token.
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs2::FileExt;
+use tokio::time::{Duration, Instant};
+
+/// Storage of the last use of each token.
+///
+/// Tokens are identified by a key derived from the token, so that the token itself is not stored.
+/// The times are wall-clock times, so that they can be shared between processes.
+///
+/// The methods are called synchronously from async requests; an implementation should not block for long.
+pub trait RateLimitStore: Send + Sync {
+    /// Last use of the token, if recorded; it can be in the future, when a request is already scheduled.
+    fn last_use(&self, token_key: &str) -> std::io::Result<Option<SystemTime>>;
+
+    /// Atomically replaces the last use of the token with the value computed from the previous one;
+    /// `None` from `update` keeps the previous value.
+    fn update_last_use(&self, token_key: &str, update: &mut dyn FnMut(Option<SystemTime>) -> Option<SystemTime>) -> std::io::Result<()>;
+}
+
+/// Keeps the last use in memory; share one instance between clients of the same process.
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    last_use: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    fn last_use(&self, token_key: &str) -> std::io::Result<Option<SystemTime>> {
+        Ok(self.last_use.lock().unwrap().get(token_key).copied())
+    }
+
+    fn update_last_use(&self, token_key: &str, update: &mut dyn FnMut(Option<SystemTime>) -> Option<SystemTime>) -> std::io::Result<()> {
+        let mut last_use = self.last_use.lock().unwrap();
+        if let Some(value) = update(last_use.get(token_key).copied()) {
+            last_use.insert(token_key.to_string(), value);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the last use in a file, locked while being updated; use it to share tokens between processes.
+///
+/// The file contains one line per token: the token key and the last use in milliseconds since Unix epoch.
+///
+/// Opening and locking the file blocks the executor thread of the calling task, until the other processes
+/// release the lock; they hold it only for reading and writing the few lines, so the file should be on a local disk.
+#[derive(Debug, Clone)]
+pub struct FileRateLimitStore {
+    path: PathBuf,
+}
+
+impl FileRateLimitStore {
+    /// The file is created on first use.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn open(&self) -> std::io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+    }
+}
+
+fn read_entries(file: &mut File) -> std::io::Result<HashMap<String, SystemTime>> {
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let entries = text.lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(key, millis)| {
+            let millis = millis.trim().parse().ok()?;
+            Some((key.to_string(), UNIX_EPOCH + Duration::from_millis(millis)))
+        })
+        .collect();
+    Ok(entries)
+}
+
+impl RateLimitStore for FileRateLimitStore {
+    fn last_use(&self, token_key: &str) -> std::io::Result<Option<SystemTime>> {
+        let mut file = self.open()?;
+        FileExt::lock_shared(&file)?;
+        let entries = read_entries(&mut file);
+        FileExt::unlock(&file)?;
+        Ok(entries?.get(token_key).copied())
+    }
+
+    fn update_last_use(&self, token_key: &str, update: &mut dyn FnMut(Option<SystemTime>) -> Option<SystemTime>) -> std::io::Result<()> {
+        let mut file = self.open()?;
+        FileExt::lock_exclusive(&file)?;
+        let result = (|| {
+            let mut entries = read_entries(&mut file)?;
+            let value = match update(entries.get(token_key).copied()) {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+            entries.insert(token_key.to_string(), value);
+            let mut text = String::new();
+            for (key, time) in &entries {
+                let millis = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                text.push_str(&format!("{} {}\n", key, millis));
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(text.as_bytes())
+        })();
+        FileExt::unlock(&file)?;
+        result
+    }
+}
+
+/// Key identifying the token in [RateLimitStore]; FNV-1a hash, which is stable across builds and platforms.
+fn token_key(token: &str) -> String {
+    let hash = token.bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+pub(crate) struct RateLimiter {
+    request_rate: Duration,
+    store: Arc<dyn RateLimitStore>,
+    token_key: String,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(request_rate: Duration, store: Arc<dyn RateLimitStore>, token: &str) -> Self {
+        Self {
+            request_rate,
+            store,
+            token_key: token_key(token),
+        }
+    }
+
+    /// The current time of the monotonic clock, and of the wall clock used by the store;
+    /// read anew for each operation, so that the wall clock of all processes is used as it is.
+    fn now() -> (Instant, SystemTime) {
+        (Instant::now(), SystemTime::now())
+    }
+
+    /// Converts the time from the store to the monotonic clock, relative to the pair from [Self::now].
+    fn to_instant((instant, now): (Instant, SystemTime), time: SystemTime) -> Instant {
+        match time.duration_since(now) {
+            Ok(duration) => instant + duration,
+            Err(_) => instant,
+        }
+    }
+
+    /// The earliest time when a request could be sent, without reserving it.
+    pub(crate) fn next_slot(&self) -> Instant {
+        let clock = Self::now();
+        let now = clock.1;
+        let last_use = self.store.last_use(&self.token_key).unwrap_or_else(|e| {
+            log::warn!("Failed to read rate limit store: {}", e);
+            None
+        });
+        let next_slot = last_use.map_or(now, |last_use| (last_use + self.request_rate).max(now));
+        Self::to_instant(clock, next_slot)
+    }
+
+    /// Reserves the next free slot, if `accept` agrees with it; the caller is expected to wait until the returned time.
+    /// The slot is checked under the lock of the store, so that a concurrent caller cannot take it in between.
+    ///
+    /// When the store fails, the request is allowed immediately; the server then answers `409 Conflict` if it was too early.
+    pub(crate) fn reserve_if<F: Fn(Instant) -> bool>(&self, accept: F) -> Option<Instant> {
+        let clock = Self::now();
+        let now = clock.1;
+        let request_rate = self.request_rate;
+        let mut slot = None;
+        let result = self.store.update_last_use(&self.token_key, &mut |last_use| {
+            let next_slot = last_use.map_or(now, |last_use| (last_use + request_rate).max(now));
+            slot = Some(next_slot).filter(|&next_slot| accept(Self::to_instant(clock, next_slot)));
+            slot
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to update rate limit store: {}", e);
+            slot = Some(now).filter(|&now| accept(Self::to_instant(clock, now)));
+        }
+        slot.map(|slot| Self::to_instant(clock, slot))
+    }
+
+    /// Reserves the next free slot unconditionally.
+    #[cfg(test)]
+    pub(crate) fn reserve(&self) -> Instant {
+        self.reserve_if(|_| true).expect("accepted slot")
+    }
+
+    /// Waits for the given reserved slot.
+    pub(crate) async fn wait_for(slot: Instant) {
+        let now = Instant::now();
+        if now < slot {
+            log::trace!("Delaying next call to FIO API; duration {}", slot.duration_since(now).as_millis());
+            tokio::time::sleep_until(slot).await;
+        }
+    }
+
+    /// Called when the server refused the request as too early (`409 Conflict`),
+    /// probably because the token was used by someone else too.
+    pub(crate) fn postpone(&self) {
+        let now = SystemTime::now();
+        let result = self.store.update_last_use(&self.token_key, &mut |last_use| {
+            Some(last_use.map_or(now, |last_use| last_use.max(now)))
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to update rate limit store: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::time::{Duration, Instant};
+
+    use super::{FileRateLimitStore, MemoryRateLimitStore, RateLimiter, RateLimitStore};
+
+    const RATE: Duration = Duration::from_secs(30);
+
+    fn limiter(store: Arc<dyn RateLimitStore>) -> RateLimiter {
+        RateLimiter::new(RATE, store, "TOKEN")
+    }
+
+    /// Seconds since start, rounded; the wall clock keeps running while the tokio clock is paused.
+    fn secs(start: Instant, slot: Instant) -> u64 {
+        slot.duration_since(start).as_secs_f64().round() as u64
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_callers_get_consecutive_slots() {
+        let limiter = Arc::new(limiter(Arc::new(MemoryRateLimitStore::new())));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move {
+                    RateLimiter::wait_for(limiter.reserve()).await;
+                    secs(start, Instant::now())
+                })
+            })
+            .collect();
+        let mut times = vec![];
+        for task in tasks {
+            times.push(task.await.unwrap());
+        }
+        assert_eq!(vec![0, 30, 60], times);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_postpone() {
+        let limiter = limiter(Arc::new(MemoryRateLimitStore::new()));
+        let start = Instant::now();
+        RateLimiter::wait_for(limiter.reserve()).await;
+        tokio::time::advance(Duration::from_secs(10)).await;
+        limiter.postpone();
+        RateLimiter::wait_for(limiter.reserve()).await;
+        assert_eq!(40, secs(start, Instant::now()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shared_memory_store() {
+        let store = Arc::new(MemoryRateLimitStore::new());
+        let first = limiter(store.clone());
+        let second = limiter(store);
+        let other_token = RateLimiter::new(RATE, Arc::new(MemoryRateLimitStore::new()), "OTHER");
+        let start = Instant::now();
+        assert_eq!(0, secs(start, first.reserve()));
+        assert_eq!(30, secs(start, second.next_slot()));
+        assert_eq!(30, secs(start, second.reserve()));
+        assert_eq!(60, secs(start, first.reserve()));
+        assert_eq!(0, secs(start, other_token.reserve()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reserve_if() {
+        let limiter = limiter(Arc::new(MemoryRateLimitStore::new()));
+        let start = Instant::now();
+        let deadline = start + Duration::from_secs(10);
+        assert!(limiter.reserve_if(|slot| slot <= deadline).is_some());
+        assert_eq!(None, limiter.reserve_if(|slot| slot <= deadline));
+        assert_eq!(30, secs(start, limiter.next_slot()), "refused slot stays free");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_file_store() -> std::io::Result<()> {
+        let path = std::env::temp_dir().join(format!("fio-api-rate-limit-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // separate store instances stand for separate processes
+        let first = limiter(Arc::new(FileRateLimitStore::new(&path)));
+        let second = limiter(Arc::new(FileRateLimitStore::new(&path)));
+        let start = Instant::now();
+        assert_eq!(0, secs(start, first.reserve()));
+        assert_eq!(30, secs(start, second.reserve()));
+        assert_eq!(60, secs(start, first.next_slot()));
+        let content = std::fs::read_to_string(&path)?;
+        assert!(!content.contains("TOKEN"), "token must not be stored: {}", content);
+        assert_eq!(1, content.lines().count());
+        std::fs::remove_file(&path)
+    }
+}
@@ -1,14 +1,20 @@
 use std::collections::HashMap;
-use std::io::{BufRead, Cursor, ErrorKind};
+use std::io::{BufRead, Cursor};
 
 use chrono::NaiveDate;
 use csv::{DeserializeRecordsIntoIter, Reader};
 use reqwest::Response;
 
-use crate::{csvdata, FioError};
+use crate::{csvdata, Amount};
 use crate::csvdata::FioTransactionsRecord;
-use crate::error::FioError::OtherError;
-use crate::error::parse_xml_error;
+use crate::camt053::Camt053Statement;
+use crate::error::error_from_response;
+use crate::gpc::GpcStatement;
+use crate::jsondata::JsonStatement;
+use crate::mt940::Mt940Statement;
+use crate::ofx::OfxStatement;
+use crate::statement::{Statement, StatementParser};
+use crate::xmldata::XmlStatement;
 
 /// Translation of CSV response.
 /// The text will be typically received from calls to [Fio REST API](crate::export::FioExportReq):
@@ -57,33 +63,67 @@ impl FioResponse {
                 let cursor = Cursor::new(bytes);
                 Ok(Self::from(cursor))
             }
-            404 => Err(FioError::BadRequest),
-            409 => Err(FioError::InvalidTiming),
-            413 => Err(FioError::TooManyRows),
-            500 => Err(parse_xml_error(response).await),
-            _ => Err(OtherError { code: "other".to_string(), message: response.status().canonical_reason().unwrap_or("?").to_string() })
+            _ => Err(error_from_response(response).await),
         }
     }
 
-    pub fn info(&mut self) -> std::io::Result<FioResponseInfo> {
+    pub fn info(&mut self) -> crate::Result<FioResponseInfo> {
         if self.cursor.position() > 0 {
-            return Err(std::io::Error::new(ErrorKind::Unsupported, "Info part was already read from the stream"));
+            return Err(crate::error::FioError::InvalidData("Info part was already read from the stream".to_string()));
         }
         FioResponseInfo::read(&mut self.cursor)
     }
 
-    pub fn csv_reader(mut self) -> std::io::Result<Reader<Cursor<Vec<u8>>>> {
+    pub fn csv_reader(mut self) -> crate::Result<Reader<Cursor<Vec<u8>>>> {
         FioResponseInfo::skip(&mut self.cursor)?;
         Ok(csv::ReaderBuilder::new()
             .delimiter(b';')
             .from_reader(self.cursor))
     }
 
-    pub fn data(self) -> std::io::Result<DeserializeRecordsIntoIter<Cursor<Vec<u8>>, FioTransactionsRecord>> {
+    pub fn data(self) -> crate::Result<DeserializeRecordsIntoIter<Cursor<Vec<u8>>, FioTransactionsRecord>> {
         let csv_reader = self.csv_reader()?;
         Ok(csv_reader.into_deserialize())
     }
 
+    /// Parses the whole response as JSON, see [crate::TxFormat::Json] and [crate::ReportFormat::Json].
+    pub fn json(self) -> crate::Result<JsonStatement> {
+        JsonStatement::parse(self.cursor.get_ref())
+    }
+
+    /// Parses the whole response as Fio XML, see [crate::TxFormat::FioXml] and [crate::ReportFormat::FioXml].
+    pub fn fio_xml(self) -> crate::Result<XmlStatement> {
+        let text = std::str::from_utf8(self.cursor.get_ref())?;
+        XmlStatement::parse(text)
+    }
+
+    /// Parses the whole response as MT940 statement, see [crate::ReportFormat::Mt940].
+    pub fn mt940(self) -> crate::Result<Mt940Statement> {
+        let text = std::str::from_utf8(self.cursor.get_ref())?;
+        Mt940Statement::parse(text)
+    }
+
+    /// Parses the whole response as camt.053 statement, see [crate::ReportFormat::CbaXml] and [crate::ReportFormat::SbaXml].
+    pub fn camt053(self) -> crate::Result<Camt053Statement> {
+        let text = std::str::from_utf8(self.cursor.get_ref())?;
+        Camt053Statement::parse(text)
+    }
+
+    /// Parses the whole response as GPC (ABO), see [crate::TxFormat::Gpc] and [crate::ReportFormat::Gpc].
+    pub fn gpc(self) -> crate::Result<GpcStatement> {
+        GpcStatement::parse(self.cursor.get_ref())
+    }
+
+    /// Parses the whole response as OFX, see [crate::TxFormat::Ofx] and [crate::ReportFormat::Ofx].
+    pub fn ofx(self) -> crate::Result<OfxStatement> {
+        OfxStatement::parse(self.cursor.get_ref())
+    }
+
+    /// Parses the whole response with given parser, typically the format used in the request.
+    pub fn statement(self, parser: &dyn StatementParser) -> crate::Result<Statement> {
+        parser.parse_statement(self.cursor.get_ref())
+    }
+
     pub fn into_inner(self) -> Cursor<Vec<u8>> {
         self.cursor
     }
@@ -93,21 +133,25 @@ impl FioResponse {
     }
 }
 
-const INFO_ACCOUNT_ID: &'static str = "accountId";
-const INFO_BANK_ID: &'static str = "bankId";
-const INFO_CURRENCY: &'static str = "currency";
-const INFO_IBAN: &'static str = "iban";
-const INFO_BIC: &'static str = "bic";
-const INFO_OPENING_BALANCE: &'static str = "openingBalance";
-const INFO_CLOSING_BALANCE: &'static str = "closingBalance";
-const INFO_DATE_START: &'static str = "dateStart";
-const INFO_DATE_END: &'static str = "dateEnd";
-const INFO_ID_FROM: &'static str = "idFrom";
-const INFO_ID_TO: &'static str = "idTo";
+pub(crate) const INFO_ACCOUNT_ID: &str = "accountId";
+pub(crate) const INFO_BANK_ID: &str = "bankId";
+pub(crate) const INFO_CURRENCY: &str = "currency";
+pub(crate) const INFO_IBAN: &str = "iban";
+pub(crate) const INFO_BIC: &str = "bic";
+pub(crate) const INFO_OPENING_BALANCE: &str = "openingBalance";
+pub(crate) const INFO_CLOSING_BALANCE: &str = "closingBalance";
+pub(crate) const INFO_DATE_START: &str = "dateStart";
+pub(crate) const INFO_DATE_END: &str = "dateEnd";
+pub(crate) const INFO_ID_FROM: &str = "idFrom";
+pub(crate) const INFO_ID_TO: &str = "idTo";
+pub(crate) const INFO_YEAR_LIST: &str = "yearList";
+pub(crate) const INFO_ID_LIST: &str = "idList";
+pub(crate) const INFO_ID_LAST_DOWNLOAD: &str = "idLastDownload";
 
 const UNICODE_BOM: char = '\u{feff}';
 
 /// Representation of the initial "info" part of FIO csv file.
+#[derive(Debug)]
 pub struct FioResponseInfo {
     info_headers: HashMap<String, String>,
 }
@@ -120,7 +164,7 @@ impl From<HashMap<String, String>> for FioResponseInfo {
 
 impl FioResponseInfo {
     /// Read from cursor
-    pub fn read(cursor: &mut Cursor<Vec<u8>>) -> std::io::Result<Self> {
+    pub fn read(cursor: &mut Cursor<Vec<u8>>) -> crate::Result<Self> {
         let mut line = String::new();
         let mut info_headers = HashMap::new();
         while cursor.read_line(&mut line)? > 0 {
@@ -141,12 +185,32 @@ impl FioResponseInfo {
         Ok(Self::from(info_headers))
     }
 
-    pub fn skip(cursor: &mut Cursor<Vec<u8>>) -> std::io::Result<()> {
+    /// Sets an info field, using the same textual representation as the CSV header would.
+    pub(crate) fn insert<S: ToString>(&mut self, key: &str, value: S) {
+        self.info_headers.insert(key.to_string(), value.to_string());
+    }
+
+    /// Sets IBAN, together with account number and bank code derived from it if it is a Czech IBAN.
+    pub(crate) fn insert_iban(&mut self, iban: &str) {
+        self.insert(INFO_IBAN, iban);
+        if iban.starts_with("CZ") && iban.len() == 24 && iban.is_ascii() {
+            self.insert(INFO_BANK_ID, &iban[4..8]);
+            let prefix = iban[8..14].trim_start_matches('0');
+            let number = iban[14..].trim_start_matches('0');
+            if prefix.is_empty() {
+                self.insert(INFO_ACCOUNT_ID, number);
+            } else {
+                self.insert(INFO_ACCOUNT_ID, format!("{}-{}", prefix, number));
+            }
+        }
+    }
+
+    pub fn skip(cursor: &mut Cursor<Vec<u8>>) -> crate::Result<()> {
         // if position is not 0, we suppose that info part was already read
         if cursor.position() == 0 {
             let mut line = String::new();
             while cursor.read_line(&mut line)? > 0 {
-                if let None = line.find(';') {
+                if !line.contains(';') {
                     break;
                 }
                 line.clear();
@@ -192,13 +256,13 @@ impl FioResponseInfo {
         self.get_info(INFO_BIC)
     }
 
-    pub fn opening_balance(&self) -> crate::Result<f64> {
+    pub fn opening_balance(&self) -> crate::Result<Amount> {
         let s = self.get_info(INFO_OPENING_BALANCE)?;
         csvdata::fio_decimal::parse_fio_decimal(s)
             .map_err(crate::error::FioError::from)
     }
 
-    pub fn closing_balance(&self) -> crate::Result<f64> {
+    pub fn closing_balance(&self) -> crate::Result<Amount> {
         let s = self.get_info(INFO_CLOSING_BALANCE)?;
         csvdata::fio_decimal::parse_fio_decimal(s)
             .map_err(crate::error::FioError::from)
@@ -231,6 +295,7 @@ mod tests {
 
     use chrono::NaiveDate;
 
+    use crate::{Amount, FioError, FioResponse};
     use crate::error::Result;
     use crate::FioResponseInfo;
 
@@ -254,7 +319,7 @@ ID pohybu;Datum;Objem;Měna;Protiúčet;Název protiúčtu;Kód banky;Název ban
     impl FioResponseInfo {
         fn sample1() -> Result<Self> {
             let mut cursor = Cursor::new(SAMPLE1.as_bytes().to_vec());
-            Ok(FioResponseInfo::read(&mut cursor)?)
+            FioResponseInfo::read(&mut cursor)
         }
     }
 
@@ -263,7 +328,7 @@ ID pohybu;Datum;Objem;Měna;Protiúčet;Název protiúčtu;Kód banky;Název ban
         let info = FioResponseInfo::sample1()?;
         let balance = info.opening_balance()?;
         println!("balance = {:?}", balance);
-        assert_eq!(4789.51_f64, balance);
+        assert_eq!(Amount::from_hundredths(478951), balance);
         Ok(())
     }
 
@@ -272,7 +337,15 @@ ID pohybu;Datum;Objem;Měna;Protiúčet;Název protiúčtu;Kód banky;Název ban
         let info = FioResponseInfo::sample1()?;
         let date = info.date_end()?;
         println!("date = {:?}", date);
-        assert_eq!(NaiveDate::from_ymd(2021, 6, 30), date);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), date);
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_read_twice() -> Result<()> {
+        let mut response = FioResponse::from(Cursor::new(SAMPLE1.as_bytes().to_vec()));
+        assert_eq!("2345678901", response.info()?.account_id()?);
+        assert!(matches!(response.info(), Err(FioError::InvalidData(_))));
         Ok(())
     }
 }
@@ -0,0 +1,120 @@
+//! How long to keep trying when the API refuses a request for timing reasons (doc/8.3),
+//! or when the request fails for reasons that may pass by themselves.
+use tokio::time::{Duration, Instant};
+
+/// Limits of repeating a failed request.
+///
+/// Requests refused with `409 Conflict` are repeated in the next free slot of the token.
+/// Exports failing on connection errors or on temporary server errors (`5xx`) are repeated
+/// after a backoff; errors that cannot pass by themselves, like `404` or `413`, are never repeated.
+/// See [crate::FioError::is_retryable].
+///
+/// ```
+/// use std::time::Duration;
+/// let policy = fio_api::RetryPolicy::default()
+///     .max_attempts(2)
+///     .max_wait(Duration::from_secs(60))
+///     .backoff(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    max_wait: Option<Duration>,
+    backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, one second of initial backoff, with no limit on the time spent waiting.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            max_wait: None,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Maximal number of attempts, including the first one; at least one attempt is always made.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Overall deadline of the call: maximal time from the call until the (last) request is sent;
+    /// requests which would have to wait longer fail immediately.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = Some(max_wait);
+        self
+    }
+
+    /// Delay before the first repetition after a connection or server error; it doubles with each further attempt.
+    /// The repetition still waits for the next free slot of the token, if that comes later.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// `true` if another attempt may follow the given number of attempts.
+    pub(crate) fn can_retry(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    /// Delay after the given number of failed attempts; saturates instead of overflowing.
+    pub(crate) fn delay_after(&self, attempts: u32) -> Duration {
+        self.backoff.saturating_mul(2_u32.saturating_pow(attempts.saturating_sub(1).min(16)))
+    }
+
+    /// Time of the next attempt after the given number of failed attempts; `None` when not representable.
+    pub(crate) fn retry_at(&self, attempts: u32) -> Option<Instant> {
+        Instant::now().checked_add(self.delay_after(attempts))
+    }
+
+    /// `true` if a request sent at `slot` would exceed the maximal wait of a call started at `started`.
+    pub(crate) fn is_too_late(&self, started: Instant, slot: Instant) -> bool {
+        match self.max_wait {
+            None => false,
+            // deadline too far to be represented cannot be exceeded
+            Some(max_wait) => started.checked_add(max_wait).is_some_and(|deadline| slot > deadline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::{Duration, Instant};
+
+    use super::RetryPolicy;
+
+    #[test]
+    fn test_limits() {
+        let policy = RetryPolicy::default()
+            .max_attempts(0)
+            .max_wait(Duration::from_secs(10));
+        assert!(!policy.can_retry(1));
+        let now = Instant::now();
+        assert!(!policy.is_too_late(now, now + Duration::from_secs(10)));
+        assert!(policy.is_too_late(now, now + Duration::from_secs(11)));
+        assert!(RetryPolicy::default().can_retry(2));
+        assert!(!RetryPolicy::default().is_too_late(now, now + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default().backoff(Duration::from_millis(100));
+        assert_eq!(Duration::from_millis(100), policy.delay_after(1));
+        assert_eq!(Duration::from_millis(200), policy.delay_after(2));
+        assert_eq!(Duration::from_millis(400), policy.delay_after(3));
+    }
+
+    #[test]
+    fn test_huge_backoff() {
+        let policy = RetryPolicy::default()
+            .backoff(Duration::MAX / 2)
+            .max_wait(Duration::MAX);
+        assert_eq!(Duration::MAX, policy.delay_after(20));
+        assert_eq!(None, policy.retry_at(20));
+        let now = Instant::now();
+        assert!(!policy.is_too_late(now, now + Duration::from_secs(3600)));
+    }
+}
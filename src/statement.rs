@@ -0,0 +1,159 @@
+//! Format-agnostic representation of downloaded transactions.
+//!
+//! Each data format has its own parser ([FioResponse::json], [FioResponse::gpc] etc.) with format-specific details;
+//! [Statement] keeps only the common part, so that the caller can switch formats without changing the processing code.
+use std::io::Cursor;
+
+use crate::csvdata::FioTransactionsRecord;
+use crate::{Camt053Statement, FioError, FioResponse, FioResponseInfo, GpcStatement, JsonStatement, Mt940Statement, OfxStatement, ReportFormat, TxFormat, XmlStatement};
+
+/// Statement info and transactions, regardless of the format they were received in.
+#[derive(Debug)]
+pub struct Statement {
+    pub info: FioResponseInfo,
+    pub transactions: Vec<FioTransactionsRecord>,
+}
+
+/// Parser of the response body into [Statement].
+/// It is implemented by [TxFormat] and [ReportFormat], so that the format used for request also decides how to parse the response.
+pub trait StatementParser {
+    fn parse_statement(&self, bytes: &[u8]) -> crate::Result<Statement>;
+}
+
+impl Statement {
+    /// Parses the CSV format, with the info part at the beginning.
+    pub fn parse_csv(bytes: &[u8]) -> crate::Result<Self> {
+        let mut response = FioResponse::from(Cursor::new(bytes.to_vec()));
+        let info = response.info()?;
+        let transactions = response.data()?
+            .collect::<Result<_, _>>()?;
+        Ok(Self { info, transactions })
+    }
+}
+
+impl StatementParser for TxFormat {
+    fn parse_statement(&self, bytes: &[u8]) -> crate::Result<Statement> {
+        match self {
+            TxFormat::Csv => Statement::parse_csv(bytes),
+            TxFormat::Gpc => GpcStatement::parse(bytes).map(Statement::from),
+            TxFormat::Json => JsonStatement::parse(bytes).map(Statement::from),
+            TxFormat::Ofx => OfxStatement::parse(bytes).map(Statement::from),
+            TxFormat::FioXml => XmlStatement::parse(std::str::from_utf8(bytes)?).map(Statement::from),
+            TxFormat::Html => Err(FioError::UnsupportedFormat(Into::<&str>::into(self).to_string())),
+        }
+    }
+}
+
+impl StatementParser for ReportFormat {
+    fn parse_statement(&self, bytes: &[u8]) -> crate::Result<Statement> {
+        match self {
+            ReportFormat::Csv => TxFormat::Csv.parse_statement(bytes),
+            ReportFormat::Gpc => TxFormat::Gpc.parse_statement(bytes),
+            ReportFormat::Json => TxFormat::Json.parse_statement(bytes),
+            ReportFormat::Ofx => TxFormat::Ofx.parse_statement(bytes),
+            ReportFormat::FioXml => TxFormat::FioXml.parse_statement(bytes),
+            ReportFormat::Mt940 => Mt940Statement::parse(std::str::from_utf8(bytes)?).map(Statement::from),
+            ReportFormat::CbaXml | ReportFormat::SbaXml =>
+                Camt053Statement::parse(std::str::from_utf8(bytes)?).map(Statement::from),
+            ReportFormat::Html | ReportFormat::Pdf => Err(FioError::UnsupportedFormat(self.to_string())),
+        }
+    }
+}
+
+impl From<JsonStatement> for Statement {
+    fn from(json: JsonStatement) -> Self {
+        Self {
+            info: FioResponseInfo::from(&json.info),
+            transactions: json.transactions,
+        }
+    }
+}
+
+impl From<XmlStatement> for Statement {
+    fn from(xml: XmlStatement) -> Self {
+        Self { info: xml.info, transactions: xml.transactions }
+    }
+}
+
+impl From<Mt940Statement> for Statement {
+    fn from(mt940: Mt940Statement) -> Self {
+        Self { info: mt940.info, transactions: mt940.transactions }
+    }
+}
+
+impl From<Camt053Statement> for Statement {
+    fn from(camt: Camt053Statement) -> Self {
+        let transactions = camt.entries.into_iter()
+            .map(|entry| entry.record)
+            .collect();
+        Self { info: camt.info, transactions }
+    }
+}
+
+impl From<GpcStatement> for Statement {
+    fn from(gpc: GpcStatement) -> Self {
+        Self { info: gpc.info, transactions: gpc.transactions }
+    }
+}
+
+impl From<OfxStatement> for Statement {
+    fn from(ofx: OfxStatement) -> Self {
+        Self { info: ofx.info, transactions: ofx.transactions }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Amount;
+    use crate::error::Result;
+    use crate::{ReportFormat, TxFormat};
+
+    use super::StatementParser;
+
+    const CSV: &str = "accountId;2345678901
+bankId;2010
+currency;CZK
+iban;CZ6220100000002345678901
+bic;FIOBCZPPXXX
+openingBalance;4789,51
+closingBalance;6186,58
+dateStart;01.06.2021
+dateEnd;30.06.2021
+idFrom;23771345451
+idTo;23794028126
+
+ID pohybu;Datum;Objem;Měna;Protiúčet;Název protiúčtu;Kód banky;Název banky;KS;VS;SS;Uživatelská identifikace;Zpráva pro příjemce;Typ;Provedl;Upřesnění;Komentář;BIC;ID pokynu
+23771345451;01.06.2021;1500,00;CZK;2702016516;Novák, Jan;2010;Fio banka, a.s.;;123;;Novák, Jan;nájem;Příjem převodem uvnitř banky;;;;;26010123456
+23794028126;30.06.2021;-102,93;CZK;;;;;;;;;;Platba kartou;;;;;
+";
+
+    const MT940: &str = ":20:X
+:25:2345678901/2010
+:60F:C210531CZK4789,51
+:61:2106010601C1500,00NTRF23771345451
+:61:2106300630D102,93NMSC23794028126
+:62F:C210630CZK6186,58
+-
+";
+
+    #[test]
+    fn test_parse_by_format() -> Result<()> {
+        let csv = TxFormat::Csv.parse_statement(CSV.as_bytes())?;
+        let mt940 = ReportFormat::Mt940.parse_statement(MT940.as_bytes())?;
+        for statement in [csv, mt940] {
+            assert_eq!("2345678901", statement.info.account_id()?);
+            assert_eq!(Amount::from_hundredths(618658), statement.info.closing_balance()?);
+            assert_eq!(2, statement.transactions.len());
+            assert_eq!(23771345451, statement.transactions[0].id_tx);
+            assert_eq!(Amount::from_hundredths(-10293), statement.transactions[1].value);
+            assert_eq!(None, statement.transactions[1].id_command);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(ReportFormat::Pdf.parse_statement(b"%PDF").is_err());
+        assert!(TxFormat::Html.parse_statement(b"<html/>").is_err());
+    }
+}
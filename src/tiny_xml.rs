@@ -64,8 +64,17 @@ impl TinyXml {
     /// Does nothing if the text is empty.
     pub fn simple(&mut self, elem: &str, text: &str) -> Result<()> {
         if text.is_empty() { return Ok(()) }
+        self.simple_attrs(elem, &[], text)
+    }
+
+    /// Adds an element with attributes and text content, even if the text is empty.
+    pub fn simple_attrs(&mut self, elem: &str, attrs: &[Attribute], text: &str) -> Result<()> {
         self.indent()?;
-        writeln!(self.output, "<{elem}>{text}</{elem}>",
+        write!(self.output, "<{}", elem)?;
+        for (name, value) in attrs {
+            write!(self.output, r#" {}="{}""#, name, escape_attr(value))?;
+        }
+        writeln!(self.output, ">{text}</{elem}>",
                  elem = elem,
                  text = escape_textcontent(text),
         )?;
@@ -0,0 +1,175 @@
+//! doc/5.1: Fio XML format of transactions and reports (`transactions.xml`).
+//!
+//! Schema: https://www.fio.cz/xsd/IBSchema.xsd
+//!
+//! Each transaction column is an element named `column_N` with attributes `name` and `id`;
+//! the `id` is used to recognize the column, so that the parser does not depend on Czech names.
+use std::collections::HashMap;
+
+use roxmltree::{Document, Node};
+
+use crate::csvdata::{fio_date, FioTransactionsRecord};
+use crate::response::{INFO_DATE_END, INFO_DATE_START};
+use crate::{FioError, FioResponseInfo};
+
+/// Parsed Fio XML response, as received for [crate::TxFormat::FioXml] or [crate::ReportFormat::FioXml].
+#[derive(Debug)]
+pub struct XmlStatement {
+    pub info: FioResponseInfo,
+    pub transactions: Vec<FioTransactionsRecord>,
+}
+
+impl XmlStatement {
+    /// Parses the complete `AccountStatement` document.
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let doc = Document::parse(text)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("AccountStatement") {
+            return Err(unexpected_element(root));
+        }
+        let info = match child(root, "Info") {
+            None => return Err(FioError::MissingInfoField("Info".to_string())),
+            Some(info) => parse_info(info)?,
+        };
+        let transactions = match child(root, "TransactionList") {
+            None => vec![],
+            Some(list) => list.children()
+                .filter(|n| n.has_tag_name("Transaction"))
+                .map(parse_transaction)
+                .collect::<crate::Result<_>>()?,
+        };
+        Ok(Self { info, transactions })
+    }
+}
+
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn unexpected_element(node: Node) -> FioError {
+    FioError::InvalidData(format!("Unexpected element '{}'", node.tag_name().name()))
+}
+
+/// Converts `<Info>` into the CSV-like representation; dates are translated to the CSV format.
+fn parse_info(info: Node) -> crate::Result<FioResponseInfo> {
+    let mut result = FioResponseInfo::from(HashMap::new());
+    for field in info.children().filter(Node::is_element) {
+        let key = field.tag_name().name();
+        let value = field.text().unwrap_or("").trim();
+        if value.is_empty() {
+            continue;
+        }
+        if key == INFO_DATE_START || key == INFO_DATE_END {
+            let date = fio_date::parse_iso_date(value)?;
+            result.insert(key, fio_date::format_fio_date(&date));
+        } else {
+            result.insert(key, value);
+        }
+    }
+    Ok(result)
+}
+
+fn parse_transaction(tx: Node) -> crate::Result<FioTransactionsRecord> {
+    let mut columns = HashMap::new();
+    for column in tx.children().filter(Node::is_element) {
+        let id = match column.attribute("id") {
+            Some(id) => id,
+            // fallback to the numeric suffix of element name
+            None => column.tag_name().name().trim_start_matches("column_"),
+        };
+        let id: u16 = id.parse()?;
+        let value = column.text().unwrap_or("").trim();
+        if !value.is_empty() {
+            columns.insert(id, value.to_string());
+        }
+    }
+    FioTransactionsRecord::from_columns(&columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::csvdata::TxType;
+    use crate::Amount;
+    use crate::error::Result;
+
+    use crate::StatementParser;
+
+    use super::XmlStatement;
+
+    const SAMPLE1: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AccountStatement>
+  <Info>
+    <accountId>2345678901</accountId>
+    <bankId>2010</bankId>
+    <currency>CZK</currency>
+    <iban>CZ6220100000002345678901</iban>
+    <bic>FIOBCZPPXXX</bic>
+    <openingBalance>4789.51</openingBalance>
+    <closingBalance>19753.26</closingBalance>
+    <dateStart>2021-06-01+02:00</dateStart>
+    <dateEnd>2021-06-30+02:00</dateEnd>
+    <idFrom>23771345451</idFrom>
+    <idTo>23794028126</idTo>
+  </Info>
+  <TransactionList>
+    <Transaction>
+      <column_22 name="ID pohybu" id="22">23771345451</column_22>
+      <column_0 name="Datum" id="0">2021-06-01+02:00</column_0>
+      <column_1 name="Objem" id="1">1500.00</column_1>
+      <column_14 name="Měna" id="14">CZK</column_14>
+      <column_2 name="Protiúčet" id="2">2702016516</column_2>
+      <column_10 name="Název protiúčtu" id="10">Novák, Jan</column_10>
+      <column_3 name="Kód banky" id="3">2010</column_3>
+      <column_12 name="Název banky" id="12">Fio banka, a.s.</column_12>
+      <column_5 name="VS" id="5">123</column_5>
+      <column_16 name="Zpráva pro příjemce" id="16">nájem &amp; služby</column_16>
+      <column_8 name="Typ" id="8">Příjem převodem uvnitř banky</column_8>
+      <column_17 name="ID pokynu" id="17">26010123456</column_17>
+    </Transaction>
+    <Transaction>
+      <column_22 name="ID pohybu" id="22">23794028126</column_22>
+      <column_0 name="Datum" id="0">2021-06-30+02:00</column_0>
+      <column_1 name="Objem" id="1">-102.93</column_1>
+      <column_14 name="Měna" id="14">CZK</column_14>
+      <column_8 name="Typ" id="8">Platba kartou</column_8>
+    </Transaction>
+  </TransactionList>
+</AccountStatement>
+"#;
+
+    #[test]
+    fn test_parse_xml() -> Result<()> {
+        let statement = XmlStatement::parse(SAMPLE1)?;
+        assert_eq!("2345678901", statement.info.account_id()?);
+        assert_eq!(Amount::from_hundredths(478951), statement.info.opening_balance()?);
+        assert_eq!(NaiveDate::from_ymd_opt(2021, 6, 30).unwrap(), statement.info.date_end()?);
+        assert_eq!(2, statement.transactions.len());
+
+        let tx = &statement.transactions[0];
+        assert_eq!(23771345451, tx.id_tx);
+        assert_eq!(Amount::from_hundredths(150000), tx.value);
+        assert_eq!("nájem & služby", tx.message);
+        assert!(matches!(tx.tx_type, TxType::FioIncome));
+        assert_eq!(Some(26010123456), tx.id_command);
+
+        let tx = &statement.transactions[1];
+        assert_eq!(Amount::from_hundredths(-10293), tx.value);
+        assert_eq!("", tx.b_account);
+        assert_eq!(None, tx.id_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_root() {
+        assert!(matches!(XmlStatement::parse("<response/>"), Err(crate::FioError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut bytes = SAMPLE1.as_bytes().to_vec();
+        bytes[40] = 0xff;
+        assert!(matches!(crate::TxFormat::FioXml.parse_statement(&bytes), Err(crate::FioError::Utf8Error(_))));
+    }
+}